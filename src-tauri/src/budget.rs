@@ -0,0 +1,39 @@
+//! Daily budget: progress in the menu and a warning marker in the tray once
+//! today's cost goes over.
+
+/// Marker put in front of the tray title while over budget
+pub(crate) const OVER_BUDGET_MARKER: &str = "⚠️";
+
+/// Budgets of zero or less count as unset
+pub(crate) fn valid_budget(budget: Option<f64>) -> Option<f64> {
+    budget.filter(|budget| *budget > 0.0)
+}
+
+/// "Budget: $4.21 / $10.00 (42%)"
+pub(crate) fn progress_line(cost: f64, budget: f64) -> String {
+    format!(
+        "Budget: {} / {} ({:.0}%)",
+        crate::money(cost),
+        crate::money(budget),
+        cost / budget * 100.0
+    )
+}
+
+/// Strictly over: reaching the budget exactly is still within it
+pub(crate) fn is_exceeded(cost: f64, budget: f64) -> bool {
+    cost > budget
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_reads_spend_against_budget() {
+        assert_eq!(progress_line(4.21, 10.0), "Budget: $4.21 / $10.00 (42%)");
+        assert_eq!(progress_line(12.5, 10.0), "Budget: $12.50 / $10.00 (125%)");
+        assert!(!is_exceeded(10.0, 10.0));
+        assert!(is_exceeded(10.01, 10.0));
+        assert_eq!(valid_budget(Some(0.0)), None);
+    }
+}
@@ -0,0 +1,173 @@
+//! Showing costs in another currency. ccusage reports USD; with `currency`
+//! set, the rate against USD is fetched once a day and cached on disk, so the
+//! last known rate keeps working offline.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+const RATES_URL: &str = "https://open.er-api.com/v6/latest/USD";
+
+/// A rate as fetched, kept on disk between launches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CachedRate {
+    /// ISO 4217 code, upper case
+    pub code: String,
+    /// Units of `code` per US dollar
+    pub usd_rate: f64,
+    pub fetched_on: chrono::NaiveDate,
+}
+
+// Rate in use for display; None shows USD
+static ACTIVE_RATE: Mutex<Option<CachedRate>> = Mutex::new(None);
+
+pub(crate) fn active() -> Option<CachedRate> {
+    ACTIVE_RATE.lock().unwrap().clone()
+}
+
+pub(crate) fn symbol(code: &str) -> String {
+    match code {
+        "USD" => "$".to_string(),
+        "EUR" => "€".to_string(),
+        "GBP" => "£".to_string(),
+        "JPY" | "CNY" => "¥".to_string(),
+        "KRW" => "₩".to_string(),
+        "INR" => "₹".to_string(),
+        "CHF" => "CHF ".to_string(),
+        other => format!("{} ", other),
+    }
+}
+
+// Currencies conventionally shown without minor units
+fn decimals(code: &str) -> usize {
+    match code {
+        "JPY" | "KRW" => 0,
+        _ => 2,
+    }
+}
+
+/// `cost_usd` in the given rate's currency, or as USD without one
+pub(crate) fn format(cost_usd: f64, rate: Option<&CachedRate>) -> String {
+    match rate {
+        Some(rate) => format!(
+            "{}{:.*}",
+            symbol(&rate.code),
+            decimals(&rate.code),
+            cost_usd * rate.usd_rate
+        ),
+        None => format!("${:.2}", cost_usd),
+    }
+}
+
+fn cache_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("ccusage-menubar").join("exchange_rate.json"))
+}
+
+fn load_cache() -> Option<CachedRate> {
+    let contents = std::fs::read_to_string(cache_path()?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn save_cache(rate: &CachedRate) {
+    let Some(path) = cache_path() else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if let Ok(json) = serde_json::to_string(rate) {
+        let _ = std::fs::write(path, json);
+    }
+}
+
+// The rate for `code` from an open.er-api.com response
+fn parse_rate(json: &str, code: &str) -> Option<f64> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    value
+        .get("rates")?
+        .get(code)?
+        .as_f64()
+        .filter(|rate| rate.is_finite() && *rate > 0.0)
+}
+
+async fn fetch_rate(code: &str) -> Option<f64> {
+    let output = Command::new("curl")
+        .args(["-fsS", "--max-time", "10", RATES_URL])
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_rate(&String::from_utf8_lossy(&output.stdout), code)
+}
+
+// Whether `cached` can be used for `code` without fetching today
+fn is_fresh(cached: Option<&CachedRate>, code: &str, today: chrono::NaiveDate) -> bool {
+    cached.is_some_and(|cached| cached.code == code && cached.fetched_on == today)
+}
+
+/// Bring the active rate in line with the `currency` setting, fetching at most
+/// once a day. A failed fetch keeps the last rate for that currency.
+pub(crate) async fn refresh(currency: Option<&str>) {
+    let Some(code) = currency.map(|code| code.trim().to_uppercase()).filter(|code| !code.is_empty() && code != "USD")
+    else {
+        *ACTIVE_RATE.lock().unwrap() = None;
+        return;
+    };
+    let today = chrono::Local::now().date_naive();
+    let cached = active().or_else(load_cache).filter(|cached| cached.code == code);
+    if is_fresh(cached.as_ref(), &code, today) {
+        *ACTIVE_RATE.lock().unwrap() = cached;
+        return;
+    }
+    let rate = match fetch_rate(&code).await {
+        Some(usd_rate) => {
+            let rate = CachedRate { code: code.clone(), usd_rate, fetched_on: today };
+            save_cache(&rate);
+            Some(rate)
+        }
+        None => {
+            eprintln!("Couldn't fetch the {} exchange rate; using the last known one", code);
+            cached
+        }
+    };
+    *ACTIVE_RATE.lock().unwrap() = rate;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(code: &str, usd_rate: f64) -> CachedRate {
+        CachedRate {
+            code: code.to_string(),
+            usd_rate,
+            fetched_on: chrono::NaiveDate::from_ymd_opt(2025, 6, 2).unwrap(),
+        }
+    }
+
+    #[test]
+    fn formats_in_the_chosen_currency() {
+        assert_eq!(format(3.21, None), "$3.21");
+        assert_eq!(format(3.21, Some(&rate("EUR", 0.92))), "€2.95");
+        assert_eq!(format(3.21, Some(&rate("JPY", 150.0))), "¥482");
+        assert_eq!(format(1.0, Some(&rate("SEK", 10.5))), "SEK 10.50");
+    }
+
+    #[test]
+    fn reads_rates_and_refetches_daily() {
+        let response = r#"{"result":"success","base_code":"USD","rates":{"USD":1,"EUR":0.92,"XXX":0}}"#;
+        assert_eq!(parse_rate(response, "EUR"), Some(0.92));
+        assert_eq!(parse_rate(response, "XXX"), None);
+        assert_eq!(parse_rate(response, "GBP"), None);
+
+        let cached = rate("EUR", 0.92);
+        let day = cached.fetched_on;
+        assert!(is_fresh(Some(&cached), "EUR", day));
+        assert!(!is_fresh(Some(&cached), "EUR", day.succ_opt().unwrap()));
+        assert!(!is_fresh(Some(&cached), "GBP", day));
+    }
+}
@@ -0,0 +1,87 @@
+//! Daily history written out for spreadsheets and expense reports.
+
+use std::path::Path;
+
+use crate::DailyEntry;
+
+/// Which layout to write, picked from the chosen file's extension
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub(crate) fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => ExportFormat::Json,
+            _ => ExportFormat::Csv,
+        }
+    }
+}
+
+// Quote a CSV field only when it needs it
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// One row per day, models joined with ";" in name order
+fn to_csv(entries: &[DailyEntry]) -> String {
+    let mut csv = String::from("date,input_tokens,cached_input_tokens,output_tokens,total_tokens,cost_usd,models\n");
+    for entry in entries {
+        let mut models: Vec<&str> = entry.models.keys().map(String::as_str).collect();
+        models.sort();
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            csv_field(&entry.date),
+            entry.input_tokens,
+            entry.cached_input_tokens,
+            entry.output_tokens,
+            entry.total_tokens,
+            entry.cost_usd.map(|cost| format!("{:.4}", cost)).unwrap_or_default(),
+            csv_field(&models.join(";"))
+        ));
+    }
+    csv
+}
+
+pub(crate) fn render(entries: &[DailyEntry], format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Csv => Ok(to_csv(entries)),
+        ExportFormat::Json => serde_json::to_string_pretty(entries).map_err(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ModelStats;
+    use std::collections::HashMap;
+
+    #[test]
+    fn csv_has_a_row_per_day() {
+        let day = DailyEntry {
+            date: "Jun 02, 2025".to_string(),
+            input_tokens: 1000,
+            cached_input_tokens: 200,
+            output_tokens: 500,
+            total_tokens: 1700,
+            cost_usd: Some(3.5),
+            models: HashMap::from([
+                ("gpt-5".to_string(), ModelStats::default()),
+                ("gpt-5-codex".to_string(), ModelStats::default()),
+            ]),
+        };
+        let csv = render(&[day], ExportFormat::Csv).unwrap();
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("\"Jun 02, 2025\",1000,200,500,1700,3.5000,gpt-5;gpt-5-codex")
+        );
+        assert_eq!(ExportFormat::for_path(Path::new("usage.JSON")), ExportFormat::Json);
+        assert_eq!(ExportFormat::for_path(Path::new("usage")), ExportFormat::Csv);
+    }
+}
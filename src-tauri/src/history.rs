@@ -0,0 +1,185 @@
+//! Usage kept on disk in SQLite: every successful refresh is stored by day (and
+//! the current block by id), so trends and all-time figures outlive what
+//! ccusage still reports and the menu has data while the CLI is unavailable.
+
+use std::path::PathBuf;
+
+use rusqlite::{params, Connection};
+
+use crate::{parse_daily_date, BlockData, DailyEntry};
+
+pub(crate) fn db_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("ccusage-menubar").join("history.sqlite"))
+}
+
+fn open() -> Result<Connection, String> {
+    let path = db_path().ok_or("No data directory available")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let conn = Connection::open(&path).map_err(|e| e.to_string())?;
+    migrate(&conn).map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS daily (
+            date TEXT PRIMARY KEY,
+            cost_usd REAL,
+            entry TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS blocks (
+            id TEXT PRIMARY KEY,
+            recorded_at TEXT NOT NULL,
+            cost_usd REAL,
+            block TEXT NOT NULL
+        );",
+    )
+}
+
+// Days are keyed by ISO date whatever layout ccusage used, so a day reported
+// in both layouts is stored once. Later reports of a day replace earlier ones.
+fn save_daily(conn: &Connection, entries: &[DailyEntry]) -> rusqlite::Result<()> {
+    let mut statement = conn.prepare(
+        "INSERT INTO daily (date, cost_usd, entry) VALUES (?1, ?2, ?3)
+         ON CONFLICT(date) DO UPDATE SET cost_usd = excluded.cost_usd, entry = excluded.entry",
+    )?;
+    for entry in entries {
+        let Some(date) = parse_daily_date(&entry.date) else {
+            continue;
+        };
+        let stored = DailyEntry {
+            date: date.format("%Y-%m-%d").to_string(),
+            ..entry.clone()
+        };
+        let json = serde_json::to_string(&stored).unwrap_or_default();
+        statement.execute(params![stored.date, entry.cost_usd, json])?;
+    }
+    Ok(())
+}
+
+fn save_block(conn: &Connection, block: &BlockData) -> rusqlite::Result<()> {
+    let json = serde_json::to_string(block).unwrap_or_default();
+    conn.execute(
+        "INSERT INTO blocks (id, recorded_at, cost_usd, block) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(id) DO UPDATE SET recorded_at = excluded.recorded_at,
+             cost_usd = excluded.cost_usd, block = excluded.block",
+        params![block.id, chrono::Local::now().to_rfc3339(), block.cost_usd, json],
+    )?;
+    Ok(())
+}
+
+// Every stored day, oldest first; rows that no longer parse are skipped
+fn load_daily(conn: &Connection) -> rusqlite::Result<Vec<DailyEntry>> {
+    let mut statement = conn.prepare("SELECT entry FROM daily ORDER BY date")?;
+    let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+    Ok(rows
+        .filter_map(|json| serde_json::from_str(&json.ok()?).ok())
+        .collect())
+}
+
+fn sync_with(conn: &Connection, fresh: &[DailyEntry], block: Option<&BlockData>) -> rusqlite::Result<Vec<DailyEntry>> {
+    save_daily(conn, fresh)?;
+    if let Some(block) = block {
+        save_block(conn, block)?;
+    }
+    load_daily(conn)
+}
+
+/// Store a refresh's days and block, then return the full stored history.
+/// Pass no days after a failed fetch to just read what's kept. Falls back to
+/// `fresh` when the database can't be used.
+pub(crate) fn sync(fresh: Vec<DailyEntry>, block: Option<&BlockData>) -> Vec<DailyEntry> {
+    match open().and_then(|conn| sync_with(&conn, &fresh, block).map_err(|e| e.to_string())) {
+        Ok(stored) if !stored.is_empty() => stored,
+        Ok(_) => fresh,
+        Err(e) => {
+            eprintln!("Usage history unavailable: {}", e);
+            fresh
+        }
+    }
+}
+
+/// Consecutive days with usage, counted back from today (or from yesterday
+/// while today has none yet, so the streak doesn't break before the day is out)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct Streak {
+    pub days: u32,
+    /// How many of the streak's latest days in a row stayed within the daily
+    /// budget; None without a budget
+    pub under_budget: Option<u32>,
+}
+
+pub(crate) fn streak(days: &[DailyEntry], today: chrono::NaiveDate, budget: Option<f64>) -> Streak {
+    let used: std::collections::HashMap<chrono::NaiveDate, f64> = days
+        .iter()
+        .filter(|entry| entry.total_tokens > 0 || entry.cost_usd.is_some_and(|cost| cost > 0.0))
+        .filter_map(|entry| Some((parse_daily_date(&entry.date)?, entry.cost_usd.unwrap_or(0.0))))
+        .collect();
+    let start = if used.contains_key(&today) { Some(today) } else { today.pred_opt() };
+    let costs: Vec<f64> = std::iter::successors(start, |date| date.pred_opt())
+        .map_while(|date| used.get(&date).copied())
+        .collect();
+    Streak {
+        days: costs.len() as u32,
+        under_budget: budget.map(|budget| costs.iter().take_while(|cost| **cost <= budget).count() as u32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn entry(date: &str, cost_usd: f64) -> DailyEntry {
+        DailyEntry {
+            date: date.to_string(),
+            input_tokens: 1000,
+            cached_input_tokens: 0,
+            output_tokens: 500,
+            total_tokens: 1500,
+            cost_usd: Some(cost_usd),
+            models: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn stored_days_outlive_the_cli_and_update_in_place() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+
+        sync_with(&conn, &[entry("May 30, 2025", 4.0), entry("2025-06-01", 1.0)], None).unwrap();
+        // ccusage later drops May 30 and revises June 1
+        let stored = sync_with(&conn, &[entry("Jun 01, 2025", 2.5), entry("2025-06-02", 3.0)], None).unwrap();
+        let days: Vec<(&str, Option<f64>)> = stored.iter().map(|e| (e.date.as_str(), e.cost_usd)).collect();
+        assert_eq!(
+            days,
+            vec![("2025-05-30", Some(4.0)), ("2025-06-01", Some(2.5)), ("2025-06-02", Some(3.0))]
+        );
+
+        // A failed fetch reads back what's kept
+        assert_eq!(sync_with(&conn, &[], None).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn streak_counts_back_through_days_with_usage() {
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 6, 5).unwrap();
+        let days = vec![
+            entry("2025-06-01", 9.0),
+            entry("2025-06-02", 12.0),
+            entry("2025-06-03", 4.0),
+            entry("2025-06-04", 6.0),
+        ];
+        // Today has no usage yet, so the streak runs through yesterday
+        assert_eq!(
+            streak(&days, today, Some(10.0)),
+            Streak { days: 4, under_budget: Some(2) }
+        );
+        let mut with_today = days.clone();
+        with_today.push(entry("Jun 05, 2025", 1.0));
+        assert_eq!(streak(&with_today, today, None), Streak { days: 5, under_budget: None });
+        // A day without usage breaks it
+        assert_eq!(streak(&days[..2], today, None).days, 0);
+    }
+}
@@ -1,13 +1,24 @@
+mod budget;
+mod currency;
+mod export;
+mod history;
+mod native;
+mod settings;
+mod updates;
+
+use settings::{CostPrecision, DayTimezone, LeftClickAction, Section, Settings, TitleMode, ToolPreference};
 use tauri::{
-    menu::{MenuBuilder, MenuItemBuilder},
-    tray::{TrayIconBuilder},
-    Manager,
+    menu::{CheckMenuItemBuilder, IconMenuItemBuilder, IsMenuItem, MenuBuilder, MenuItemBuilder, SubmenuBuilder},
+    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
+    Emitter, Manager,
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
 use tokio::process::Command;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct BlockData {
@@ -16,30 +27,167 @@ struct BlockData {
     start_time: String,
     #[serde(rename = "endTime")]
     end_time: String,
-    #[serde(rename = "isActive")]
+    #[serde(rename = "isActive", default, deserialize_with = "deserialize_null_as_false")]
     is_active: bool,
     #[serde(rename = "tokenCounts")]
     token_counts: TokenCounts,
-    #[serde(rename = "costUSD")]
-    cost_usd: f64,
+    // Token-only views report no cost; missing and null both read as None
+    #[serde(rename = "costUSD", default)]
+    cost_usd: Option<f64>,
     models: Vec<String>,
+    // Per-model stats: keyed entries in the daily schema, an optional
+    // modelBreakdowns list in blocks/sessions output
+    #[serde(
+        rename = "modelBreakdowns",
+        default,
+        deserialize_with = "deserialize_model_breakdowns",
+        skip_serializing
+    )]
+    model_stats: HashMap<String, ModelStats>,
+    // Only when ccusage ran with --token-limit
+    #[serde(rename = "tokenLimitStatus", default, skip_serializing_if = "Option::is_none")]
+    token_limit_status: Option<TokenLimitStatus>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+struct TokenLimitStatus {
+    limit: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct TokenCounts {
-    #[serde(rename = "inputTokens")]
+    #[serde(rename = "inputTokens", default, deserialize_with = "deserialize_token_count")]
     input_tokens: u64,
-    #[serde(rename = "outputTokens")]
+    #[serde(rename = "outputTokens", default, deserialize_with = "deserialize_token_count")]
     output_tokens: u64,
-    #[serde(rename = "cacheCreationInputTokens")]
+    #[serde(rename = "cacheCreationInputTokens", default, deserialize_with = "deserialize_token_count")]
     cache_creation_input_tokens: u64,
-    #[serde(rename = "cacheReadInputTokens")]
+    #[serde(rename = "cacheReadInputTokens", default, deserialize_with = "deserialize_token_count")]
     cache_read_input_tokens: u64,
 }
 
+/// One model's figures in ccusage's modelBreakdowns list
+#[derive(Deserialize)]
+struct ModelBreakdown {
+    #[serde(rename = "modelName")]
+    model_name: String,
+    #[serde(rename = "inputTokens", default)]
+    input_tokens: Option<u64>,
+    #[serde(rename = "outputTokens", default)]
+    output_tokens: Option<u64>,
+    #[serde(rename = "cacheReadTokens", default)]
+    cache_read_tokens: Option<u64>,
+    #[serde(rename = "cost", alias = "costUSD", default)]
+    cost: Option<f64>,
+}
+
+impl ModelBreakdown {
+    fn into_stats(self) -> (String, ModelStats) {
+        let total = [self.input_tokens, self.output_tokens, self.cache_read_tokens]
+            .iter()
+            .flatten()
+            .sum();
+        let stats = ModelStats {
+            is_fallback: None,
+            input_tokens: self.input_tokens,
+            cached_input_tokens: self.cache_read_tokens,
+            output_tokens: self.output_tokens,
+            total_tokens: Some(total),
+            cost_usd: self.cost,
+        };
+        (self.model_name, stats)
+    }
+}
+
+// Blocks output may list per-model figures; fold them into the same stats the
+// daily schema provides. Null or absent means the flat schema.
+fn deserialize_model_breakdowns<'de, D>(deserializer: D) -> Result<HashMap<String, ModelStats>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let breakdowns = Option::<Vec<ModelBreakdown>>::deserialize(deserializer)?.unwrap_or_default();
+    Ok(breakdowns.into_iter().map(ModelBreakdown::into_stats).collect())
+}
+
+// Daily models come keyed by name from @ccusage/codex and as a
+// modelBreakdowns list from the Claude ccusage
+fn deserialize_daily_models<'de, D>(deserializer: D) -> Result<HashMap<String, ModelStats>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawModels {
+        Keyed(HashMap<String, ModelStats>),
+        Listed(Vec<ModelBreakdown>),
+    }
+
+    Ok(match Option::<RawModels>::deserialize(deserializer)? {
+        Some(RawModels::Keyed(models)) => models,
+        Some(RawModels::Listed(breakdowns)) => breakdowns.into_iter().map(ModelBreakdown::into_stats).collect(),
+        None => HashMap::new(),
+    })
+}
+
+// A null date reads like a missing one: empty, and skipped when matching days
+fn deserialize_null_as_empty<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+// Older and third-party producers omit isActive or send null; both mean inactive
+fn deserialize_null_as_false<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<bool>::deserialize(deserializer)?.unwrap_or(false))
+}
+
+// ccusage emits integers, but other producers send numeric strings or floats
+// (truncated here). Null, missing, and unreadable values count as zero rather
+// than failing the whole response.
+fn deserialize_token_count<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawCount {
+        Int(u64),
+        Float(f64),
+        Text(String),
+    }
+
+    let count = match Option::<RawCount>::deserialize(deserializer)? {
+        None => 0,
+        Some(RawCount::Int(count)) => count,
+        Some(RawCount::Float(count)) => count.max(0.0) as u64,
+        Some(RawCount::Text(text)) => {
+            let text = text.trim();
+            match text.parse::<u64>() {
+                Ok(count) => count,
+                Err(_) => match text.parse::<f64>() {
+                    Ok(count) if count.is_finite() => count.max(0.0) as u64,
+                    _ => {
+                        if !text.is_empty() {
+                            eprintln!("Ignoring unreadable token count: {:?}", text);
+                        }
+                        0
+                    }
+                },
+            }
+        }
+    };
+    Ok(count)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct BlocksResponse {
     blocks: Vec<BlockData>,
+    #[serde(default)]
+    totals: Option<Totals>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,31 +195,75 @@ struct SessionsResponse {
     sessions: Vec<BlockData>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Per-model figures are optional; older ccusage versions only report isFallback
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct ModelStats {
     #[serde(rename = "isFallback")]
     is_fallback: Option<bool>,
+    #[serde(rename = "inputTokens", default)]
+    input_tokens: Option<u64>,
+    #[serde(rename = "cachedInputTokens", default)]
+    cached_input_tokens: Option<u64>,
+    #[serde(rename = "outputTokens", default)]
+    output_tokens: Option<u64>,
+    #[serde(rename = "totalTokens", default)]
+    total_tokens: Option<u64>,
+    #[serde(rename = "costUSD", default)]
+    cost_usd: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct DailyEntry {
+    #[serde(default, deserialize_with = "deserialize_null_as_empty")]
     date: String,
-    #[serde(rename = "inputTokens")]
+    #[serde(rename = "inputTokens", default, deserialize_with = "deserialize_token_count")]
     input_tokens: u64,
-    #[serde(rename = "cachedInputTokens")]
+    #[serde(rename = "cachedInputTokens", default, deserialize_with = "deserialize_token_count")]
     cached_input_tokens: u64,
-    #[serde(rename = "outputTokens")]
+    #[serde(rename = "outputTokens", default, deserialize_with = "deserialize_token_count")]
     output_tokens: u64,
-    #[serde(rename = "totalTokens")]
+    #[serde(rename = "totalTokens", default, deserialize_with = "deserialize_token_count")]
     total_tokens: u64,
-    #[serde(rename = "costUSD")]
-    cost_usd: f64,
+    #[serde(rename = "costUSD", default)]
+    cost_usd: Option<f64>,
+    #[serde(alias = "modelBreakdowns", default, deserialize_with = "deserialize_daily_models")]
     models: HashMap<String, ModelStats>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct DailyResponse {
     daily: Vec<DailyEntry>,
+    #[serde(default)]
+    totals: Option<Totals>,
+}
+
+/// Summary ccusage may emit next to the entries. It covers every entry the
+/// CLI saw, so it stays exact even when the list is trimmed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Totals {
+    // @ccusage/codex says costUSD, the Claude ccusage says totalCost
+    #[serde(rename = "costUSD", alias = "totalCost", default)]
+    cost_usd: f64,
+    #[serde(rename = "inputTokens", default, deserialize_with = "deserialize_token_count")]
+    input_tokens: u64,
+    #[serde(rename = "outputTokens", default, deserialize_with = "deserialize_token_count")]
+    output_tokens: u64,
+    #[serde(rename = "totalTokens", default, deserialize_with = "deserialize_token_count")]
+    total_tokens: u64,
+}
+
+// Prefer ccusage's own totals; sum the entries only when it sent none
+fn resolve_totals(totals: Option<&Totals>, daily: &[DailyEntry]) -> Totals {
+    if let Some(totals) = totals {
+        return totals.clone();
+    }
+    daily.iter().fold(Totals::default(), |mut sum, entry| {
+        sum.cost_usd += entry.cost_usd.unwrap_or(0.0);
+        sum.input_tokens += entry.input_tokens;
+        sum.output_tokens += entry.output_tokens;
+        sum.total_tokens += entry.total_tokens;
+        sum
+    })
 }
 
 fn daily_to_block(entry: &DailyEntry) -> BlockData {
@@ -96,417 +288,3878 @@ fn daily_to_block(entry: &DailyEntry) -> BlockData {
         token_counts,
         cost_usd: entry.cost_usd,
         models,
+        model_stats: entry.models.clone(),
+        token_limit_status: None,
     }
 }
 
+// Date layouts ccusage has used for daily entries, preferred first
+const DAILY_DATE_FORMATS: &[&str] = &["%b %d, %Y", "%Y-%m-%d"];
 
-#[derive(Debug, Clone)]
-struct SessionData {
-    active_block: Option<BlockData>,
-    last_updated: Option<Instant>,
-    ccusage_available: bool,
+// Pick the layout the real entries use so synthesized entries look the same
+fn detect_daily_date_format(entries: &[DailyEntry]) -> &'static str {
+    entries
+        .iter()
+        .find_map(|entry| {
+            DAILY_DATE_FORMATS
+                .iter()
+                .find(|format| chrono::NaiveDate::parse_from_str(&entry.date, format).is_ok())
+                .copied()
+        })
+        .unwrap_or(DAILY_DATE_FORMATS[0])
 }
 
-static SESSION_CACHE: Mutex<SessionData> = Mutex::new(SessionData {
-    active_block: None,
-    last_updated: None,
-    ccusage_available: false,
-});
-
-// Removed AppSettings as we now always show cost
+fn daily_date_string(date: chrono::NaiveDate, entries: &[DailyEntry]) -> String {
+    date.format(detect_daily_date_format(entries)).to_string()
+}
 
-static IS_REFRESHING: AtomicBool = AtomicBool::new(false);
+fn parse_daily_date(date: &str) -> Option<chrono::NaiveDate> {
+    DAILY_DATE_FORMATS
+        .iter()
+        .find_map(|format| chrono::NaiveDate::parse_from_str(date, format).ok())
+}
 
-// Removed settings functions as we now always show cost
+fn is_entry_for(entry: &DailyEntry, date: chrono::NaiveDate) -> bool {
+    parse_daily_date(&entry.date) == Some(date)
+}
 
-fn format_model_name(model_name: &str) -> String {
-    match model_name {
-        "claude-opus-4-20250514" => "Opus 4".to_string(),
-        "claude-sonnet-4-20250514" => "Sonnet 4".to_string(),
-        "claude-3-5-sonnet-20241022" => "Sonnet 3.5".to_string(),
-        "claude-3-haiku-20240307" => "Haiku".to_string(),
-        "gpt-5-codex" => "GPT-5 Codex".to_string(),
-        "gpt-5" => "GPT-5".to_string(),
-        _ => {
-            if model_name.contains("opus") {
-                "Opus".to_string()
-            } else if model_name.contains("sonnet") {
-                "Sonnet".to_string()
-            } else if model_name.contains("haiku") {
-                "Haiku".to_string()
-            } else if model_name.contains("gpt-5-codex") {
-                "GPT-5 Codex".to_string()
-            } else if model_name == "gpt-5" {
-                "GPT-5".to_string()
-            } else {
-                model_name.to_string()
-            }
-        }
+// Today's entry, or a zero entry dated in ccusage's own format. Entries whose
+// date is empty or unreadable are skipped, so the zero entry only stands in
+// when no readable entry is for today.
+fn today_entry(entries: &[DailyEntry], today: chrono::NaiveDate) -> DailyEntry {
+    let skipped = entries
+        .iter()
+        .filter(|entry| parse_daily_date(&entry.date).is_none())
+        .count();
+    if skipped > 0 {
+        eprintln!("Skipping {} daily entries without a readable date", skipped);
+    }
+    if let Some(entry) = entries.iter().find(|entry| is_entry_for(entry, today)) {
+        return entry.clone();
+    }
+    DailyEntry {
+        date: daily_date_string(today, entries),
+        input_tokens: 0,
+        cached_input_tokens: 0,
+        output_tokens: 0,
+        total_tokens: 0,
+        cost_usd: Some(0.0),
+        models: HashMap::new(),
     }
 }
 
-async fn fetch_session_data() -> (Option<BlockData>, bool) {
-    // Try multiple approaches to find and run CLI
-    // Use login zsh so ~/.zprofile (Homebrew path, etc.) is loaded; avoid interactive ~/.zshrc
-    let shell_commands = vec![
-        ("/bin/zsh", vec![
-            "-l",
-            "-c",
-            "NVM_DIR=\"${NVM_DIR:-$HOME/.nvm}\"; [ -s \"$NVM_DIR/nvm.sh\" ] && . \"$NVM_DIR/nvm.sh\"; npm exec --yes @ccusage/codex@latest -- daily --json",
-        ]),
-        ("/bin/zsh", vec![
-            "-l",
-            "-c",
-            "NVM_DIR=\"${NVM_DIR:-$HOME/.nvm}\"; [ -s \"$NVM_DIR/nvm.sh\" ] && . \"$NVM_DIR/nvm.sh\"; npx @ccusage/codex@latest daily --json",
-        ]),
-        ("/bin/zsh", vec![
-            "-l",
-            "-c",
-            "NVM_DIR=\"${NVM_DIR:-$HOME/.nvm}\"; [ -s \"$NVM_DIR/nvm.sh\" ] && . \"$NVM_DIR/nvm.sh\"; ccusage daily --json",
-        ]),
-        // Fallbacks without login shell
-        ("sh", vec!["-c", "ccusage daily --json"]),
-        ("sh", vec!["-c", "npx @ccusage/codex@latest daily --json"]),
-    ];
-
-    for (cmd, args) in shell_commands {
-        let output = Command::new(cmd)
-            .args(&args)
-            .output()
-            .await;
-
-        match output {
-            Ok(output) if output.status.success() => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-
-                // Try to parse the response with multiple schemas for compatibility
-                if let Ok(response) = serde_json::from_str::<DailyResponse>(&stdout) {
-                    // Prefer today's entry; if missing, show 0.00 for today
-                    let today = chrono::Local::now().format("%b %d, %Y").to_string();
-                    if let Some(entry) = response.daily.iter().find(|d| d.date == today) {
-                        let block = daily_to_block(entry);
-                        return (Some(block), true);
-                    } else {
-                        let zero = DailyEntry {
-                            date: today,
-                            input_tokens: 0,
-                            cached_input_tokens: 0,
-                            output_tokens: 0,
-                            total_tokens: 0,
-                            cost_usd: 0.0,
-                            models: HashMap::new(),
-                        };
-                        let block = daily_to_block(&zero);
-                        return (Some(block), true);
-                    }
-                }
-                if let Ok(response) = serde_json::from_str::<SessionsResponse>(&stdout) {
-                    let active_block = response
-                        .sessions
-                        .into_iter()
-                        .find(|block| block.is_active);
-                    return (active_block, true);
-                }
+// Keep only the newest `max_days` entries, oldest first. Entries with
+// unreadable dates sort before everything else, so they go first.
+fn cap_daily_history(mut daily: Vec<DailyEntry>, max_days: usize) -> Vec<DailyEntry> {
+    daily.sort_by_key(|entry| parse_daily_date(&entry.date));
+    if daily.len() > max_days {
+        daily.drain(..daily.len() - max_days);
+    }
+    daily
+}
 
-                if let Ok(response) = serde_json::from_str::<BlocksResponse>(&stdout) {
-                    let active_block = response
-                        .blocks
-                        .into_iter()
-                        .find(|block| block.is_active);
-                    return (active_block, true);
-                }
+// Sums of the entries from `start` through `today`, for the week and month
+// submenus
+fn period_totals(history: &[DailyEntry], start: chrono::NaiveDate, today: chrono::NaiveDate) -> Totals {
+    let in_period: Vec<DailyEntry> = history
+        .iter()
+        .filter(|entry| parse_daily_date(&entry.date).is_some_and(|date| date >= start && date <= today))
+        .cloned()
+        .collect();
+    resolve_totals(None, &in_period)
+}
 
-                if let Ok(block) = serde_json::from_str::<BlockData>(&stdout) {
-                    return (Some(block), true);
-                }
+// Cost and token lines for one period's submenu
+fn period_submenu(id: &str, label: &str, totals: &Totals) -> MenuEntry {
+    MenuEntry::Submenu {
+        id: id.to_string(),
+        label: label.to_string(),
+        entries: vec![
+            MenuEntry::disabled(format!("{}_cost", id), format!("Cost: {}", money(totals.cost_usd))),
+            MenuEntry::disabled(
+                format!("{}_tokens", id),
+                format!(
+                    "Tokens: In {} / Out {}",
+                    format_tokens_k(totals.input_tokens),
+                    format_tokens_k(totals.output_tokens)
+                ),
+            ),
+        ],
+    }
+}
 
-                if let Ok(blocks) = serde_json::from_str::<Vec<BlockData>>(&stdout) {
-                    let active_block = blocks.into_iter().find(|block| block.is_active);
-                    return (active_block, true);
-                }
+// Month-to-date spend extrapolated over the whole calendar month
+fn projected_month_cost(history: &[DailyEntry], today: chrono::NaiveDate) -> f64 {
+    use chrono::Datelike;
+    let month_start = today.with_day(1).unwrap_or(today);
+    let next_month = month_start + chrono::Months::new(1);
+    let days_in_month = (next_month - month_start).num_days() as f64;
+    month_to_date_cost(history, today) / today.day() as f64 * days_in_month
+}
 
-                eprintln!("Failed to parse CLI response with known schemas");
-                eprintln!("Response was: {}", stdout);
-                continue;
-            }
-            Ok(output) => {
-                eprintln!("ccusage command failed with status: {}", output.status);
-                eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
-                continue;
-            }
-            Err(e) => {
-                eprintln!("Failed to execute command '{}': {}", cmd, e);
-                continue;
-            }
+// Projected month against the average of earlier complete months, e.g.
+// "This month vs avg: ▲ 18%". The oldest month only counts if history starts
+// on its first day. None without at least one complete month.
+fn month_vs_average(history: &[DailyEntry], today: chrono::NaiveDate) -> Option<String> {
+    use chrono::Datelike;
+    let dated: Vec<(chrono::NaiveDate, f64)> = history
+        .iter()
+        .filter_map(|entry| Some((parse_daily_date(&entry.date)?, entry.cost_usd.unwrap_or(0.0))))
+        .collect();
+    let earliest = dated.iter().map(|(date, _)| *date).min()?;
+    let current = (today.year(), today.month());
+    let mut months: std::collections::BTreeMap<(i32, u32), f64> = std::collections::BTreeMap::new();
+    for (date, cost) in &dated {
+        let month = (date.year(), date.month());
+        let partial = month == (earliest.year(), earliest.month()) && earliest.day() != 1;
+        if month < current && !partial {
+            *months.entry(month).or_default() += cost;
         }
     }
+    if months.is_empty() {
+        return None;
+    }
+    let average = months.values().sum::<f64>() / months.len() as f64;
+    if average <= 0.0 {
+        return None;
+    }
+    let change = (projected_month_cost(history, today) - average) / average * 100.0;
+    let arrow = if change >= 0.5 {
+        "▲"
+    } else if change <= -0.5 {
+        "▼"
+    } else {
+        "="
+    };
+    Some(format!("This month vs avg: {} {:.0}%", arrow, change.abs()))
+}
 
-    eprintln!("All attempts to fetch session data failed");
-    (None, false)
+// Days the menu sparkline covers, and how far back the dashboard series stays
+// daily before older days are folded into weekly buckets
+const SPARKLINE_DAYS: usize = 14;
+const DAILY_RESOLUTION_DAYS: usize = 90;
+
+/// One point of a cost series: a day, or a week (dated by its Monday) once downsampled
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+struct SeriesPoint {
+    date: chrono::NaiveDate,
+    cost_usd: f64,
 }
 
-// Removed fetch_blocks_data and fetch_week_data functions as they are no longer needed
+// Cost for each of the `days` days ending with `today`, oldest first; days
+// without an entry count as zero
+fn recent_daily_costs(history: &[DailyEntry], today: chrono::NaiveDate, days: usize) -> Vec<f64> {
+    (0..days as u64)
+        .rev()
+        .map(|back| {
+            let date = today - chrono::Days::new(back);
+            history
+                .iter()
+                .find(|entry| is_entry_for(entry, date))
+                .and_then(|entry| entry.cost_usd)
+                .unwrap_or(0.0)
+        })
+        .collect()
+}
 
-async fn get_debug_info() -> String {
-    let mut debug_info = String::new();
-    
-    // Get PATH environment variable
-    debug_info.push_str("Environment:\n");
-    if let Ok(path) = std::env::var("PATH") {
-        debug_info.push_str(&format!("Default PATH: {}\n", path));
-    } else {
-        debug_info.push_str("Default PATH: (not set)\n");
-    }
-    
-    // Explain shell used for checks
-    debug_info.push_str("Checks run in login zsh; nvm sourced if present\n\n");
-    
-    // Test commands with login zsh + optional nvm sourcing
-    debug_info.push_str("Command availability (login zsh + nvm):\n");
-    
-    let commands_to_test = vec![
-        ("which npx".to_string(), "npx location"),
-        ("which node".to_string(), "node location"),
-        ("which ccusage".to_string(), "ccusage location"),
-        ("npx --version".to_string(), "npx version"),
-        ("node --version".to_string(), "node version"),
-        ("ccusage --version 2>&1 || echo 'not found'".to_string(), "ccusage version"),
-    ];
-    
-    let nvm_source = r#"NVM_DIR="${NVM_DIR:-$HOME/.nvm}"; [ -s "$NVM_DIR/nvm.sh" ] && . "$NVM_DIR/nvm.sh""#;
-    for (cmd, desc) in commands_to_test {
-        let cmd_with_nvm = format!("{}; {}", nvm_source, cmd);
-        let output = Command::new("/bin/zsh")
-            .args(&["-l", "-c", &cmd_with_nvm])
-            .output()
-            .await;
-            
-        match output {
-            Ok(output) if output.status.success() => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                debug_info.push_str(&format!("{}: {}\n", desc, stdout.trim()));
-            }
-            Ok(output) => {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                if stderr.trim().is_empty() {
-                    debug_info.push_str(&format!("{}: not found\n", desc));
-                } else {
-                    debug_info.push_str(&format!("{}: {}\n", desc, stderr.trim()));
-                }
-            }
-            Err(e) => {
-                debug_info.push_str(&format!("{}: error - {}\n", desc, e));
+// Days in the drawn bar chart, and its size in points; each day is a 7pt bar
+// with a 3pt gap
+const WEEK_CHART_DAYS: usize = 7;
+const WEEK_CHART_WIDTH: u32 = 70;
+const WEEK_CHART_HEIGHT: u32 = 16;
+
+// Bar chart of `values` as RGBA, tallest bar full height. Mid grey reads on
+// both light and dark menus; days without spend get a 1pt baseline.
+fn bar_chart(values: &[f64], width: u32, height: u32) -> MenuIcon {
+    const BAR: [u8; 4] = [128, 128, 128, 255];
+    let mut rgba = vec![0u8; (width * height * 4) as usize];
+    let max = values.iter().copied().fold(0.0, f64::max);
+    let slot = width / values.len().max(1) as u32;
+    let bar_width = slot.saturating_sub(3).max(1);
+    for (index, value) in values.iter().enumerate() {
+        let bar_height = if max > 0.0 {
+            ((value / max) * height as f64).round().max(1.0) as u32
+        } else {
+            1
+        };
+        let left = index as u32 * slot;
+        for y in height - bar_height.min(height)..height {
+            for x in left..(left + bar_width).min(width) {
+                let offset = ((y * width + x) * 4) as usize;
+                rgba[offset..offset + 4].copy_from_slice(&BAR);
             }
         }
     }
-    
-    // Test @ccusage/codex with extended PATH
-    debug_info.push_str("\nTesting @ccusage/codex:\n");
-    let ccusage_cmd = format!(
-        r#"{}; npm exec --yes @ccusage/codex@latest -- --version || npx @ccusage/codex@latest --version"#,
-        nvm_source
-    );
-    let ccusage_output = Command::new("/bin/zsh")
-        .args(&["-l", "-c", &ccusage_cmd])
-        .output()
-        .await;
-        
-    match ccusage_output {
-        Ok(output) => {
-            if output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                debug_info.push_str(&format!("@ccusage/codex version: {}\n", stdout.trim()));
-            } else {
-                debug_info.push_str("@ccusage/codex: not available (npx @ccusage/codex@latest failed)\n");
-                if !output.stderr.is_empty() {
-                    debug_info.push_str(&format!("Error: {}\n", String::from_utf8_lossy(&output.stderr).trim()));
-                }
+    MenuIcon { width, height, rgba }
+}
+
+fn sparkline(values: &[f64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().fold(0.0, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            if max <= 0.0 {
+                return BARS[0];
             }
+            BARS[((value / max) * (BARS.len() - 1) as f64).round() as usize]
+        })
+        .collect()
+}
+
+// Daily points for the most recent `daily_days` days of the series; anything
+// older is summed into weekly buckets so long histories stay cheap to chart.
+// `points` must be sorted by date.
+fn downsample(points: &[SeriesPoint], daily_days: usize) -> Vec<SeriesPoint> {
+    use chrono::Datelike;
+    let Some(last) = points.last() else {
+        return Vec::new();
+    };
+    let cutoff = last.date - chrono::Days::new(daily_days as u64);
+    let mut series: Vec<SeriesPoint> = Vec::new();
+    for point in points {
+        if point.date > cutoff {
+            series.push(*point);
+            continue;
         }
-        Err(e) => {
-            debug_info.push_str(&format!("Error executing @ccusage/codex: {}\n", e));
+        let week = point.date - chrono::Days::new(point.date.weekday().num_days_from_monday() as u64);
+        match series.last_mut() {
+            Some(bucket) if bucket.date == week => bucket.cost_usd += point.cost_usd,
+            _ => series.push(SeriesPoint {
+                date: week,
+                cost_usd: point.cost_usd,
+            }),
         }
     }
-    
-    debug_info
+    series
 }
 
-async fn refresh_session_data(app_handle: &tauri::AppHandle) {
-    // Set refresh flag
-    IS_REFRESHING.store(true, Ordering::Relaxed);
-    
-    // Fetch active session data
-    let (active_block, ccusage_available) = fetch_session_data().await;
-    
-    // Update tray title with cost if there's an active session
-    let title = if let Some(ref block) = active_block {
-        format!("${:.2}", block.cost_usd)
-    } else {
-        String::new()
-    };
-    
-    // Update cache
-    {
-        let mut cache = SESSION_CACHE.lock().unwrap();
-        cache.active_block = active_block;
-        cache.last_updated = Some(Instant::now());
-        cache.ccusage_available = ccusage_available;
-    }
-    
-    // Update tray title
-    if let Some(tray) = app_handle.tray_by_id("main") {
-        let _ = tray.set_title(Some(title));
-    }
-    
-    // Rebuild and update the menu to reflect new data
-    if let Ok(new_menu) = build_menu(app_handle).await {
-        if let Some(tray) = app_handle.try_state::<Arc<tauri::tray::TrayIcon>>() {
-            let _ = tray.set_menu(Some(new_menu));
-        }
+// One point per day with a parseable date
+fn daily_points(history: &[DailyEntry]) -> Vec<SeriesPoint> {
+    history
+        .iter()
+        .filter_map(|entry| {
+            Some(SeriesPoint {
+                date: parse_daily_date(&entry.date)?,
+                cost_usd: entry.cost_usd.unwrap_or(0.0),
+            })
+        })
+        .collect()
+}
+
+// Cached history as a chartable series, downsampled past DAILY_RESOLUTION_DAYS
+#[tauri::command]
+fn get_daily_series() -> Vec<SeriesPoint> {
+    let history = SESSION_CACHE.lock().unwrap().daily_history.clone();
+    downsample(&daily_points(&history), DAILY_RESOLUTION_DAYS)
+}
+
+// Every stored day at full resolution, for the dashboard's heatmap. Falls back
+// to the cached days when the history store is unavailable.
+#[tauri::command]
+fn get_daily_history() -> Vec<SeriesPoint> {
+    let mut history = history::sync(Vec::new(), None);
+    if history.is_empty() {
+        history = SESSION_CACHE.lock().unwrap().daily_history.clone();
     }
-    
-    // Clear refresh flag
-    IS_REFRESHING.store(false, Ordering::Relaxed);
+    daily_points(&history)
 }
 
-async fn build_menu(app: &tauri::AppHandle) -> Result<tauri::menu::Menu<tauri::Wry>, Box<dyn std::error::Error>> {
-    let mut menu_builder = MenuBuilder::new(app);
+// Days in the dashboard's charts, ending today
+const DASHBOARD_DAYS: usize = 30;
 
-    // CCUsage header (simple, no timestamp)
-    let ccusage_header = MenuItemBuilder::with_id("ccusage_header", "CCUsage")
-        .build(app)?;
-    menu_builder = menu_builder.item(&ccusage_header).separator();
+/// Sent to the dashboard after every refresh, with the same data as `get_dashboard_data`
+const DASHBOARD_EVENT: &str = "dashboard-updated";
 
-    // Get data from cache
-    let (active_block, has_attempted_fetch, ccusage_available) = {
-        let cache = SESSION_CACHE.lock().unwrap();
-        (cache.active_block.clone(), cache.last_updated.is_some(), cache.ccusage_available)
-    };
-
-    // Today section
-    let session_title = MenuItemBuilder::with_id("session_title", "Today")
-        .enabled(false)
-        .build(app)?;
-    menu_builder = menu_builder.item(&session_title);
-
-    if let Some(block) = active_block {
-        // Cost and token counts
-        let input_k = block.token_counts.input_tokens as f64 / 1000.0;
-        let output_k = block.token_counts.output_tokens as f64 / 1000.0;
-        let cost_str = format!("Cost: ${:.2}", block.cost_usd);
-        let tokens_str = format!("Tokens: In {:.1}K / Out {:.1}K", input_k, output_k);
-        
-        let cost_item = MenuItemBuilder::with_id("session_cost", &cost_str)
-            .build(app)?;
-        let tokens_item = MenuItemBuilder::with_id("session_tokens", &tokens_str)
-            .build(app)?;
-        menu_builder = menu_builder.item(&cost_item).item(&tokens_item);
-        
-        // Session times (only if available)
-        let start_time = chrono::DateTime::parse_from_rfc3339(&block.start_time)
-            .ok()
-            .map(|dt| dt.with_timezone(&chrono::Local).format("%I:%M %p").to_string());
-        let end_time = chrono::DateTime::parse_from_rfc3339(&block.end_time)
-            .ok()
-            .map(|dt| dt.with_timezone(&chrono::Local).format("%I:%M %p").to_string());
-
-        if let Some(start) = start_time {
-            let session_start_item = MenuItemBuilder::with_id("session_start", &format!("Started: {}", start))
-                .build(app)?;
-            menu_builder = menu_builder.item(&session_start_item);
-        }
-        if let Some(end) = end_time {
-            let session_end_item = MenuItemBuilder::with_id("session_end", &format!("Expires: {}", end))
-                .build(app)?;
-            menu_builder = menu_builder.item(&session_end_item);
-        }
-        
-        // Models used
-        if !block.models.is_empty() {
-            menu_builder = menu_builder.separator();
-            let models_header = MenuItemBuilder::with_id("models_header", "Models used")
-                .enabled(false)
-                .build(app)?;
-            menu_builder = menu_builder.item(&models_header);
-            
-            for model in &block.models {
-                let model_name = format_model_name(model);
-                let model_item = MenuItemBuilder::with_id(
-                    &format!("model_{}", model),
-                    &model_name,
-                )
-                .build(app)?;
-                menu_builder = menu_builder.item(&model_item);
-            }
-        }
-        
-        menu_builder = menu_builder.separator();
-    } else if has_attempted_fetch {
-        // We've tried to fetch
-        let no_session = MenuItemBuilder::with_id("no_session", "No usage today")
-            .build(app)?;
-        menu_builder = menu_builder.item(&no_session);
-        
-        // Only show error if ccusage is actually not available
-        if !ccusage_available {
-            // Add helpful error message
-            let error_msg = MenuItemBuilder::with_id("error_msg", "@ccusage/codex may not be installed")
-                .enabled(false)
-                .build(app)?;
-            menu_builder = menu_builder.item(&error_msg);
-            
-            let install_msg = MenuItemBuilder::with_id("install_msg", "Install: npm i -g @ccusage/codex")
-                .build(app)?;
-            menu_builder = menu_builder.item(&install_msg);
-        }
-        
-        menu_builder = menu_builder.separator();
+/// One day of the dashboard's charts
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct DashboardDay {
+    date: chrono::NaiveDate,
+    cost_usd: f64,
+    cached_tokens: u64,
+    uncached_tokens: u64,
+}
+
+/// A model's share of the charted days
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct DashboardModel {
+    name: String,
+    input_tokens: u64,
+    output_tokens: u64,
+    cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct DashboardData {
+    days: Vec<DashboardDay>,
+    /// Costliest first; empty when the daily schema has no per-model figures
+    models: Vec<DashboardModel>,
+}
+
+// The DASHBOARD_DAYS days ending `today`, days without an entry as zero
+fn dashboard_data(history: &[DailyEntry], today: chrono::NaiveDate, group_families: bool) -> DashboardData {
+    let first = today - chrono::Days::new(DASHBOARD_DAYS as u64 - 1);
+    let days = first
+        .iter_days()
+        .take(DASHBOARD_DAYS)
+        .map(|date| {
+            let entry = history.iter().find(|entry| is_entry_for(entry, date));
+            let cached_tokens = entry.map_or(0, |entry| entry.cached_input_tokens);
+            DashboardDay {
+                date,
+                cost_usd: entry.and_then(|entry| entry.cost_usd).unwrap_or(0.0),
+                cached_tokens,
+                uncached_tokens: entry.map_or(0, |entry| entry.total_tokens.saturating_sub(cached_tokens)),
+            }
+        })
+        .collect();
+    let totals = model_totals_between(history, first, today).unwrap_or_default();
+    let models = if group_families {
+        group_model_families(totals)
     } else {
-        // Still loading
-        let loading = MenuItemBuilder::with_id("loading", "Loading...")
-            .enabled(false)
-            .build(app)?;
-        menu_builder = menu_builder.item(&loading).separator();
+        totals
+            .into_iter()
+            .map(|(model, totals)| (format_model_name(&model), totals))
+            .collect()
+    };
+    DashboardData {
+        days,
+        models: models
+            .into_iter()
+            .map(|(name, totals)| DashboardModel {
+                name,
+                input_tokens: totals.input_tokens,
+                output_tokens: totals.output_tokens,
+                cost_usd: totals.cost_usd,
+            })
+            .collect(),
     }
+}
 
+fn cached_dashboard_data() -> DashboardData {
+    let settings = settings::current();
+    let today = settings.day_timezone.date_of(&chrono::Local::now());
+    let cache = SESSION_CACHE.lock().unwrap();
+    dashboard_data(&cache.daily_history, today, settings.group_model_families)
+}
 
-    // Refresh button
-    let refresh = MenuItemBuilder::with_id("refresh", "Refresh")
-        .build(app)?;
-    menu_builder = menu_builder.item(&refresh);
+// Chart data for the dashboard; never runs ccusage
+#[tauri::command]
+fn get_dashboard_data() -> DashboardData {
+    cached_dashboard_data()
+}
 
-    // Debug info (useful for troubleshooting)
-    let debug = MenuItemBuilder::with_id("debug", "Debug Info")
-        .build(app)?;
-    menu_builder = menu_builder.item(&debug).separator();
+// Sum of the entries in today's calendar month, up to and including today
+fn month_to_date_cost(history: &[DailyEntry], today: chrono::NaiveDate) -> f64 {
+    use chrono::Datelike;
+    history
+        .iter()
+        .filter_map(|entry| parse_daily_date(&entry.date).map(|date| (date, entry.cost_usd.unwrap_or(0.0))))
+        .filter(|(date, _)| date.year() == today.year() && date.month() == today.month() && *date <= today)
+        .map(|(_, cost)| cost)
+        .sum()
+}
 
-    // Quit
-    let quit = MenuItemBuilder::with_id("quit", "Quit")
-        .accelerator("Cmd+Q")
-        .build(app)?;
-    menu_builder = menu_builder.item(&quit);
+// Today's spend against yesterday's, e.g. "Yesterday (full day): $6.10 (today +38%)".
+// ccusage's daily view has no hourly breakdown, so this compares with
+// yesterday's whole day and labels it that way rather than "to now". None
+// without an entry for yesterday.
+fn yesterday_comparison(history: &[DailyEntry], today: chrono::NaiveDate, today_cost: f64) -> Option<String> {
+    let yesterday = today.pred_opt()?;
+    let yesterday_cost = history.iter().find(|entry| is_entry_for(entry, yesterday))?.cost_usd?;
+    // A free day has no meaningful percentage
+    if yesterday_cost < 0.005 {
+        return Some(format!("Yesterday (full day): {}", money(yesterday_cost)));
+    }
+    let change = ((today_cost - yesterday_cost) / yesterday_cost * 100.0).round();
+    let sign = if change >= 0.0 { "+" } else { "−" };
+    Some(format!(
+        "Yesterday (full day): {} (today {}{:.0}%)",
+        money(yesterday_cost),
+        sign,
+        change.abs()
+    ))
+}
 
-    Ok(menu_builder.build()?)
+// Days in the rolling average shown under today's cost
+const ROLLING_AVERAGE_DAYS: usize = 7;
+
+// Mean daily cost over the ROLLING_AVERAGE_DAYS complete days before `today`,
+// days without an entry counting as zero. None without any history in that window.
+fn rolling_average(history: &[DailyEntry], today: chrono::NaiveDate) -> Option<f64> {
+    let yesterday = today.pred_opt()?;
+    let first = yesterday - chrono::Days::new(ROLLING_AVERAGE_DAYS as u64 - 1);
+    history
+        .iter()
+        .filter_map(|entry| parse_daily_date(&entry.date))
+        .any(|date| date >= first && date <= yesterday)
+        .then(|| {
+            recent_daily_costs(history, yesterday, ROLLING_AVERAGE_DAYS).iter().sum::<f64>()
+                / ROLLING_AVERAGE_DAYS as f64
+        })
 }
 
+// "7-day avg: $8.45/day", with "▲ today 2.1×" appended once today's cost
+// reaches `heavy_day_factor` times the average
+fn rolling_average_line(average: f64, today_cost: f64, heavy_day_factor: f64) -> String {
+    let line = format!("{}-day avg: {}/day", ROLLING_AVERAGE_DAYS, money(average));
+    if heavy_day_factor > 0.0 && average >= 0.005 && today_cost >= average * heavy_day_factor {
+        format!("{} ▲ today {:.1}×", line, today_cost / average)
+    } else {
+        line
+    }
+}
+
+// Days of one weekday needed before its average means anything
+const WEEKDAY_MIN_SAMPLES: usize = 2;
+
+// Average cost per weekday, Monday first, over complete days before `today`.
+// None for weekdays with fewer than WEEKDAY_MIN_SAMPLES days of history.
+fn weekday_averages(history: &[DailyEntry], today: chrono::NaiveDate) -> Vec<(chrono::Weekday, Option<f64>)> {
+    use chrono::Datelike;
+    let mut sums = [(0.0, 0usize); 7];
+    for (date, cost) in history
+        .iter()
+        .filter_map(|entry| Some((parse_daily_date(&entry.date)?, entry.cost_usd?)))
+        .filter(|(date, _)| *date < today)
+    {
+        let slot = &mut sums[date.weekday().num_days_from_monday() as usize];
+        slot.0 += cost;
+        slot.1 += 1;
+    }
+    std::iter::successors(Some(chrono::Weekday::Mon), |weekday| Some(weekday.succ()))
+        .zip(sums)
+        .map(|(weekday, (total, days))| {
+            (weekday, (days >= WEEKDAY_MIN_SAMPLES).then(|| total / days as f64))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+struct ModelTotals {
+    cost_usd: Option<f64>,
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+// Per-model sums over the 7 days ending today, costliest first. None when the
+// daily schema carries no per-model figures at all.
+fn weekly_model_totals(
+    history: &[DailyEntry],
+    today: chrono::NaiveDate,
+) -> Option<Vec<(String, ModelTotals)>> {
+    model_totals_between(history, today - chrono::Duration::days(6), today)
+}
+
+// Per-model sums over `first..=last`, like weekly_model_totals
+fn model_totals_between(
+    history: &[DailyEntry],
+    first: chrono::NaiveDate,
+    last: chrono::NaiveDate,
+) -> Option<Vec<(String, ModelTotals)>> {
+    let mut totals: HashMap<String, ModelTotals> = HashMap::new();
+    let mut has_figures = false;
+
+    for entry in history {
+        let Some(date) = parse_daily_date(&entry.date) else {
+            continue;
+        };
+        if date < first || date > last {
+            continue;
+        }
+        for (model, stats) in &entry.models {
+            let model_totals = totals.entry(model.clone()).or_default();
+            if let Some(cost) = stats.cost_usd {
+                *model_totals.cost_usd.get_or_insert(0.0) += cost;
+                has_figures = true;
+            }
+            if let Some(input) = stats.input_tokens {
+                model_totals.input_tokens += input;
+                has_figures = true;
+            }
+            if let Some(output) = stats.output_tokens {
+                model_totals.output_tokens += output;
+                has_figures = true;
+            }
+        }
+    }
+
+    if !has_figures {
+        return None;
+    }
+    Some(sorted_model_totals(totals))
+}
+
+// Costliest first, then most tokens, then by name for a stable order
+fn sorted_model_totals(totals: HashMap<String, ModelTotals>) -> Vec<(String, ModelTotals)> {
+    let mut sorted: Vec<(String, ModelTotals)> = totals.into_iter().collect();
+    sorted.sort_by(|(a_name, a), (b_name, b)| {
+        b.cost_usd
+            .unwrap_or(0.0)
+            .total_cmp(&a.cost_usd.unwrap_or(0.0))
+            .then_with(|| (b.input_tokens + b.output_tokens).cmp(&(a.input_tokens + a.output_tokens)))
+            .then_with(|| a_name.cmp(b_name))
+    });
+    sorted
+}
+
+// Family label for grouped breakdowns, using the same substring heuristics as
+// format_model_name. Models outside a known family keep their own name.
+fn model_family(model_name: &str) -> String {
+    if model_name.contains("opus") {
+        "Opus (all)".to_string()
+    } else if model_name.contains("sonnet") {
+        "Sonnet (all)".to_string()
+    } else if model_name.contains("haiku") {
+        "Haiku (all)".to_string()
+    } else if model_name.starts_with("gpt-5") {
+        "GPT-5 (all)".to_string()
+    } else {
+        format_model_name(model_name)
+    }
+}
+
+// Sums per-model totals by family, keyed by the family label
+fn group_model_families(totals: Vec<(String, ModelTotals)>) -> Vec<(String, ModelTotals)> {
+    let mut families: HashMap<String, ModelTotals> = HashMap::new();
+    for (model, model_totals) in totals {
+        let family = families.entry(model_family(&model)).or_default();
+        if let Some(cost) = model_totals.cost_usd {
+            *family.cost_usd.get_or_insert(0.0) += cost;
+        }
+        family.input_tokens += model_totals.input_tokens;
+        family.output_tokens += model_totals.output_tokens;
+    }
+    sorted_model_totals(families)
+}
+
+fn format_tokens_k(tokens: u64) -> String {
+    format!("{:.1}K", tokens as f64 / 1000.0)
+}
+
+// Tokens counted toward a single total, matching the "Tokens:" line
+fn displayed_tokens(counts: &TokenCounts, include_cache: bool) -> u64 {
+    let mut total = counts.input_tokens + counts.output_tokens;
+    if include_cache {
+        total += counts.cache_read_input_tokens;
+    }
+    total
+}
+
+// The "Tokens:" line; cache reads are only counted when the user opts in
+fn format_token_line(counts: &TokenCounts, include_cache: bool) -> String {
+    let mut line = format!(
+        "Tokens: In {} / Out {}",
+        format_tokens_k(counts.input_tokens),
+        format_tokens_k(counts.output_tokens)
+    );
+    if include_cache {
+        line.push_str(&format!(" / Cache {}", format_tokens_k(counts.cache_read_input_tokens)));
+    }
+    line
+}
+
+// "Opus 4 — $3.10 / 40K in / 12K out"; just the name when ccusage sent no
+// figures for the model
+fn model_row_label(name: &str, stats: Option<&ModelStats>) -> String {
+    let Some(stats) = stats.filter(|stats| stats.cost_usd.is_some() || stats.input_tokens.is_some()) else {
+        return name.to_string();
+    };
+    format!(
+        "{} — {} / {} in / {} out",
+        name,
+        stats.cost_usd.map(money).unwrap_or_else(|| "n/a".to_string()),
+        format_tokens_k(stats.input_tokens.unwrap_or(0)),
+        format_tokens_k(stats.output_tokens.unwrap_or(0))
+    )
+}
+
+// Markdown table of today's models, for pasting into notes
+fn model_breakdown_table(block: &BlockData) -> String {
+    let mut table = String::from("| Model | Cost | Input | Output |\n|---|---|---|---|\n");
+    for model in &block.models {
+        let stats = block.model_stats.get(model).cloned().unwrap_or_default();
+        let cost = stats
+            .cost_usd
+            .map(money)
+            .unwrap_or_else(|| "-".to_string());
+        let input = stats.input_tokens.map(format_tokens_k).unwrap_or_else(|| "-".to_string());
+        let output = stats.output_tokens.map(format_tokens_k).unwrap_or_else(|| "-".to_string());
+        table.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            format_model_name(model),
+            cost,
+            input,
+            output
+        ));
+    }
+    table
+}
+
+#[cfg(target_os = "macos")]
+fn applescript_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// Transient confirmation banner; best effort only
+fn show_notification(title: &str, message: &str) {
+    #[cfg(target_os = "macos")]
+    {
+        use std::process::Command as StdCommand;
+        let _ = StdCommand::new("osascript")
+            .args(&[
+                "-e",
+                &format!(
+                    r#"display notification "{}" with title "{}""#,
+                    applescript_escape(message),
+                    applescript_escape(title)
+                ),
+            ])
+            .spawn();
+    }
+    #[cfg(not(target_os = "macos"))]
+    println!("{}: {}", title, message);
+}
+
+// Budget and milestone alerts, honouring the user's sound and style settings
+fn show_alert(title: &str, message: &str) {
+    let alert_settings = settings::current();
+    #[cfg(target_os = "macos")]
+    {
+        use std::process::Command as StdCommand;
+        let script = match alert_settings.notification_style {
+            settings::NotificationStyle::Banner => format!(
+                r#"display notification "{}" with title "{}"{}"#,
+                applescript_escape(message),
+                applescript_escape(title),
+                if alert_settings.notification_sound { r#" sound name "default""# } else { "" }
+            ),
+            settings::NotificationStyle::Alert => format!(
+                r#"{}display alert "{}" message "{}""#,
+                if alert_settings.notification_sound { "beep\n" } else { "" },
+                applescript_escape(title),
+                applescript_escape(message)
+            ),
+        };
+        let _ = StdCommand::new("osascript").args(&["-e", &script]).spawn();
+    }
+    #[cfg(not(target_os = "macos"))]
+    println!("{}: {} ({:?})", title, message, alert_settings.notification_style);
+}
+
+
+// Compact cost for the tray. Adaptive precision compares the amount as it
+// would be shown in cents, so $99.999 reads "$100" rather than "$100.00".
+fn format_cost(cost: f64, precision: CostPrecision, whole_dollars_from: f64) -> String {
+    format_amount("$", cost, precision, whole_dollars_from)
+}
+
+fn format_amount(symbol: &str, amount: f64, precision: CostPrecision, whole_units_from: f64) -> String {
+    let cents = (amount * 100.0).round() / 100.0;
+    match precision {
+        CostPrecision::Adaptive if cents >= whole_units_from => format!("{}{:.0}", symbol, amount),
+        _ => format!("{}{:.2}", symbol, amount),
+    }
+}
+
+// A menu cost in the display currency: USD unless `currency` is set
+fn money(cost_usd: f64) -> String {
+    currency::format(cost_usd, currency::active().as_ref())
+}
+
+// USD as ccusage reports it, kept for reconciling against invoices, with the
+// converted figure alongside. The daily `rate` for `currency` takes precedence
+// over the fixed `conversion` rate when both are set.
+fn dual_cost(
+    cost_usd: Option<f64>,
+    conversion: Option<&settings::CurrencyConversion>,
+    rate: Option<&currency::CachedRate>,
+) -> String {
+    let Some(cost_usd) = cost_usd else {
+        return "n/a".to_string();
+    };
+    match (rate, conversion) {
+        (Some(rate), _) => format!("${:.2} (≈ {})", cost_usd, currency::format(cost_usd, Some(rate))),
+        (None, Some(conversion)) => format!(
+            "${:.2} (≈ {}{:.2})",
+            cost_usd,
+            conversion.symbol,
+            cost_usd * conversion.usd_rate
+        ),
+        (None, None) => format!("${:.2}", cost_usd),
+    }
+}
+
+// Hover summary for the tray, one fact per line:
+//   Today: $3.21 · 2 models
+//   Tokens: 1.5K (In 1.0K / Out 0.5K)
+//   Cache: 12.0K read / 2.0K written
+//   Block ends 17:00 (2h 13m left)
+fn tray_tooltip(
+    block: Option<&BlockData>,
+    block_view: Option<&BlockData>,
+    include_cache: bool,
+    now: chrono::DateTime<chrono::Local>,
+) -> Option<String> {
+    let block = block?;
+    let counts = &block.token_counts;
+    let cost = block
+        .cost_usd
+        .map(money)
+        .unwrap_or_else(|| "n/a".to_string());
+    let models = match block.models.len() {
+        1 => "1 model".to_string(),
+        count => format!("{} models", count),
+    };
+    let mut lines = vec![
+        format!("Today: {} · {}", cost, models),
+        format!(
+            "Tokens: {} (In {} / Out {})",
+            format_tokens_k(displayed_tokens(counts, include_cache)),
+            format_tokens_k(counts.input_tokens),
+            format_tokens_k(counts.output_tokens)
+        ),
+        format!(
+            "Cache: {} read / {} written",
+            format_tokens_k(counts.cache_read_input_tokens),
+            format_tokens_k(counts.cache_creation_input_tokens)
+        ),
+    ];
+    if let Some(view) = block_view {
+        if let (Some(left), Ok(end)) = (
+            block_time_left(view, now),
+            chrono::DateTime::parse_from_rfc3339(&view.end_time),
+        ) {
+            lines.push(format!(
+                "Block ends {} ({} left)",
+                end.with_timezone(&chrono::Local).format("%H:%M"),
+                left
+            ));
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+fn with_tray_prefix(settings: &Settings, title: String) -> String {
+    match settings.tray_prefix.as_deref().map(str::trim) {
+        Some(prefix) if !prefix.is_empty() => format!("{} {}", prefix, title),
+        _ => title,
+    }
+}
+
+/// What we know about today's spend, kept separate from the block itself so the
+/// tray can tell "no usage" apart from "couldn't fetch".
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CostState {
+    /// No successful fetch yet, or every command failed
+    Unknown,
+    /// ccusage answered and today has no spend
+    Zero,
+    /// ccusage answered with a non-zero cost for today
+    Spent(f64),
+    /// ccusage reported usage without a cost; the tray shows this many tokens
+    TokensOnly(u64),
+}
+
+impl CostState {
+    fn from_fetch(active_block: Option<&BlockData>, ccusage_available: bool) -> Self {
+        if !ccusage_available {
+            return CostState::Unknown;
+        }
+        match active_block.map(|block| (block.cost_usd, &block.token_counts)) {
+            Some((Some(cost), _)) if cost > 0.0 => CostState::Spent(cost),
+            Some((None, counts)) => {
+                let include_cache = settings::current().include_cache_tokens;
+                CostState::TokensOnly(displayed_tokens(counts, include_cache))
+            }
+            _ => CostState::Zero,
+        }
+    }
+
+    // The cost part of the title, before the prefix. None leaves it out:
+    // either nothing is known yet, or the cost is below the user's display
+    // threshold
+    fn cost_text(&self, settings: &Settings) -> Option<String> {
+        let cost = match self {
+            CostState::Unknown => return None,
+            CostState::Zero => 0.0,
+            CostState::Spent(cost) => *cost,
+            CostState::TokensOnly(tokens) => return Some(format!("{} tok", format_tokens_k(*tokens))),
+        };
+        if cost < settings.min_display_cost {
+            return None;
+        }
+        if !settings.tray_in_converted_currency {
+            return Some(format_cost(cost, settings.cost_precision, settings.whole_dollars_from));
+        }
+        // Same precedence as dual_cost: the daily rate, then the fixed one
+        Some(match (currency::active(), settings.conversion()) {
+            (Some(rate), _) => format_amount(
+                &currency::symbol(&rate.code),
+                cost * rate.usd_rate,
+                settings.cost_precision,
+                settings.whole_dollars_from,
+            ),
+            (None, Some(conversion)) => format_amount(
+                &conversion.symbol,
+                cost * conversion.usd_rate,
+                settings.cost_precision,
+                settings.whole_dollars_from,
+            ),
+            (None, None) => format_cost(cost, settings.cost_precision, settings.whole_dollars_from),
+        })
+    }
+}
+
+// Short token count for the tray: "950", "1.5K", "182K", "2.3M"
+fn format_tokens_compact(tokens: u64) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        1_000..=9_999 => format!("{:.1}K", tokens as f64 / 1_000.0),
+        10_000..=999_999 => format!("{:.0}K", tokens as f64 / 1_000.0),
+        _ => format!("{:.1}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+// The tray title in the user's display mode; every title goes through here.
+// Tokens need a block, and nothing shows until a fetch has succeeded.
+fn format_title(block: Option<&BlockData>, cost_state: CostState, settings: &Settings) -> Option<String> {
+    let tokens = || {
+        block
+            .filter(|_| cost_state != CostState::Unknown)
+            .map(|block| format_tokens_compact(displayed_tokens(&block.token_counts, settings.include_cache_tokens)))
+    };
+    let title = match settings.title_mode {
+        TitleMode::Cost => cost_state.cost_text(settings),
+        TitleMode::Tokens => tokens(),
+        // Tokens-only days already show their tokens
+        TitleMode::Both if matches!(cost_state, CostState::TokensOnly(_)) => cost_state.cost_text(settings),
+        TitleMode::Both => cost_state
+            .cost_text(settings)
+            .zip(tokens())
+            .map(|(cost, tokens)| format!("{} · {}", cost, tokens)),
+        TitleMode::IconOnly => None,
+    };
+    let over_budget = match (cost_state, budget::valid_budget(settings.daily_budget_usd)) {
+        (CostState::Spent(cost), Some(budget)) => budget::is_exceeded(cost, budget),
+        _ => false,
+    };
+    title.map(|title| with_tray_prefix(settings, title)).map(|title| {
+        if over_budget {
+            format!("{} {}", budget::OVER_BUDGET_MARKER, title)
+        } else {
+            title
+        }
+    })
+}
+
+/// Where the fetch cycle stands. Kept explicit so the menu never reads a fetch
+/// that is still running as "no usage today".
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FetchPhase {
+    /// Nothing has been fetched yet
+    Pending,
+    /// A fetch is running; `has_result` is true when an earlier one finished
+    InProgress { has_result: bool },
+    /// The last fetch finished
+    Completed,
+}
+
+impl FetchPhase {
+    // Whether the cached data reflects at least one finished fetch
+    fn has_result(&self) -> bool {
+        match self {
+            FetchPhase::Pending => false,
+            FetchPhase::InProgress { has_result } => *has_result,
+            FetchPhase::Completed => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SessionData {
+    active_block: Option<BlockData>,
+    last_updated: Option<Instant>,
+    fetch_phase: FetchPhase,
+    ccusage_available: bool,
+    cost_state: CostState,
+    last_fetch_latency: Option<Duration>,
+    avg_fetch_latency: Option<Duration>,
+    daily_history: Vec<DailyEntry>,
+    // Active 5-hour block, only fetched when show_active_block is on
+    block_view: Option<BlockData>,
+    data_source: Option<DataSource>,
+    totals: Option<Totals>,
+    last_error: Option<FetchError>,
+    // Node is there but ccusage isn't cached yet, so the first fetch installs it
+    installing_ccusage: bool,
+    // Each tool's today in multi-source mode; empty otherwise
+    per_source: Vec<(CliTool, BlockData)>,
+    // Today's costliest Claude Code projects, most expensive first
+    projects: Vec<(String, f64)>,
+    // Whether the last fetch saw today's tokens move, and when they last did
+    usage_changed: bool,
+    last_usage_change: Option<Instant>,
+    // Last fetch that got figures; last_updated also moves on failures
+    last_success: Option<Instant>,
+}
+
+impl SessionData {
+    // Nothing fetched yet
+    const EMPTY: SessionData = SessionData {
+        active_block: None,
+        last_updated: None,
+        fetch_phase: FetchPhase::Pending,
+        ccusage_available: false,
+        cost_state: CostState::Unknown,
+        last_fetch_latency: None,
+        avg_fetch_latency: None,
+        daily_history: Vec::new(),
+        block_view: None,
+        data_source: None,
+        totals: None,
+        last_error: None,
+        installing_ccusage: false,
+        per_source: Vec::new(),
+        projects: Vec::new(),
+        usage_changed: false,
+        last_usage_change: None,
+        last_success: None,
+    };
+}
+
+static SESSION_CACHE: Mutex<SessionData> = Mutex::new(SessionData::EMPTY);
+
+static IS_REFRESHING: AtomicBool = AtomicBool::new(false);
+
+// Set once Quit is chosen so background tasks stop starting new work
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+
+// Time until the next multiple of `interval` on the local wall clock, so a
+// 2-minute interval fires at :00, :02, :04 regardless of when the app started
+fn delay_to_next_boundary(now: chrono::DateTime<chrono::Local>, interval: Duration) -> Duration {
+    use chrono::Offset;
+    let interval_ms = interval.as_millis().max(1) as i64;
+    let local_ms = now.timestamp_millis() + now.offset().fix().local_minus_utc() as i64 * 1000;
+    let next_ms = (local_ms / interval_ms + 1) * interval_ms;
+    Duration::from_millis((next_ms - local_ms) as u64)
+}
+
+// Recent fetch outcomes, oldest first, for spotting intermittent failures
+const FETCH_HISTORY_LEN: usize = 20;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum FetchOutcome {
+    Success,
+    NoActiveBlock,
+    EmptyOutput,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FetchRecord {
+    timestamp: chrono::DateTime<chrono::Local>,
+    outcome: FetchOutcome,
+    latency_ms: u64,
+}
+
+static FETCH_HISTORY: Mutex<VecDeque<FetchRecord>> = Mutex::new(VecDeque::new());
+
+// Fetches slower than this are usually npx re-resolving @latest
+const SLOW_FETCH_THRESHOLD: Duration = Duration::from_secs(10);
+
+fn record_fetch(outcome: FetchOutcome, latency: Duration) {
+    let avg_latency = {
+        let mut history = FETCH_HISTORY.lock().unwrap();
+        if history.len() == FETCH_HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(FetchRecord {
+            timestamp: chrono::Local::now(),
+            outcome,
+            latency_ms: latency.as_millis() as u64,
+        });
+        let total_ms: u64 = history.iter().map(|record| record.latency_ms).sum();
+        Duration::from_millis(total_ms / history.len() as u64)
+    };
+
+    #[cfg(debug_assertions)]
+    eprintln!("Fetch took {:.1}s ({:?})", latency.as_secs_f64(), outcome);
+    if latency > SLOW_FETCH_THRESHOLD {
+        eprintln!(
+            "Slow fetch: {:.1}s; consider pinning the @ccusage/codex version or installing it globally",
+            latency.as_secs_f64()
+        );
+    }
+
+    let mut cache = SESSION_CACHE.lock().unwrap();
+    cache.last_fetch_latency = Some(latency);
+    cache.avg_fetch_latency = Some(avg_latency);
+}
+
+// Title the tray would show for today's cached cost under `settings`, for a
+// live preview while they're edited. Empty means icon-only. Changes nothing.
+#[tauri::command]
+fn preview_title(settings: Settings) -> String {
+    let cache = SESSION_CACHE.lock().unwrap();
+    title_preview(cache.active_block.as_ref(), cache.cost_state, &settings)
+}
+
+fn title_preview(block: Option<&BlockData>, cost_state: CostState, settings: &Settings) -> String {
+    format_title(block, cost_state, settings).unwrap_or_default()
+}
+
+// Full settings as JSON, proxy credentials redacted
+#[tauri::command]
+fn export_settings() -> String {
+    settings::export().0
+}
+
+// The tray's left click only opens the menu for OpenMenu; kept in step with
+// the setting when it changes
+fn apply_left_click_action(app: &tauri::AppHandle) {
+    if let Some(tray) = app.tray_by_id("main") {
+        let _ = tray.set_show_menu_on_left_click(settings::current().left_click_action.opens_menu());
+    }
+}
+
+// Replace the settings from exported JSON and apply them right away. True
+// when the JSON's command, registry or proxies were left out.
+#[tauri::command]
+fn import_settings(app: tauri::AppHandle, json: String) -> Result<bool, String> {
+    let (_, kept) = settings::import(&json)?;
+    apply_left_click_action(&app);
+    // The data source or ccusage version may have changed
+    forget_winning_command();
+    spawn_refresh(&app);
+    Ok(kept)
+}
+
+#[tauri::command]
+fn get_settings() -> Settings {
+    settings::current()
+}
+
+// Save from the Preferences window; applied like a menu toggle would be
+#[tauri::command]
+fn set_settings(app: tauri::AppHandle, settings: Settings) -> Result<(), String> {
+    let updated = settings::update(|current| *current = settings);
+    #[cfg(target_os = "macos")]
+    app.set_activation_policy(activation_policy(updated.show_dock_icon))
+        .map_err(|e| e.to_string())?;
+    apply_launch_at_login(&app, updated.launch_at_login);
+    apply_left_click_action(&app);
+    forget_winning_command();
+    spawn_refresh(&app);
+    Ok(())
+}
+
+/// What the menu currently shows, for the Preferences window
+#[derive(Debug, Clone, Serialize)]
+struct UsageSnapshot {
+    today: Option<BlockData>,
+    title: Option<String>,
+    totals: Option<Totals>,
+    data_source: Option<DataSource>,
+}
+
+#[tauri::command]
+fn get_usage_snapshot() -> UsageSnapshot {
+    let cache = SESSION_CACHE.lock().unwrap();
+    UsageSnapshot {
+        title: format_title(cache.active_block.as_ref(), cache.cost_state, &settings::current()),
+        today: cache.active_block.clone(),
+        totals: cache.totals.clone(),
+        data_source: cache.data_source,
+    }
+}
+
+const PREFERENCES_WINDOW: &str = "preferences";
+
+// The app has no windows otherwise, so the one for settings is made on demand
+fn open_preferences(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window(PREFERENCES_WINDOW) {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return;
+    }
+    let built = tauri::WebviewWindowBuilder::new(app, PREFERENCES_WINDOW, tauri::WebviewUrl::App("index.html".into()))
+        .title("CCUsage Preferences")
+        .inner_size(480.0, 600.0)
+        .resizable(false)
+        .build();
+    match built {
+        Ok(window) => {
+            let _ = window.set_focus();
+        }
+        Err(e) => eprintln!("Failed to open Preferences: {}", e),
+    }
+}
+
+const DASHBOARD_WINDOW: &str = "dashboard";
+
+// Same page as Preferences; the frontend picks the view by window label
+fn open_dashboard(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window(DASHBOARD_WINDOW) {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return;
+    }
+    let built = tauri::WebviewWindowBuilder::new(app, DASHBOARD_WINDOW, tauri::WebviewUrl::App("index.html".into()))
+        .title("CCUsage Dashboard")
+        .inner_size(820.0, 320.0)
+        .min_inner_size(480.0, 240.0)
+        .build();
+    match built {
+        Ok(window) => {
+            let _ = window.set_focus();
+        }
+        Err(e) => eprintln!("Failed to open the dashboard: {}", e),
+    }
+}
+
+#[tauri::command]
+fn get_data_source() -> Option<DataSource> {
+    SESSION_CACHE.lock().unwrap().data_source
+}
+
+#[tauri::command]
+fn get_recent_fetches() -> Vec<FetchRecord> {
+    FETCH_HISTORY.lock().unwrap().iter().cloned().collect()
+}
+
+/// The app's state in one object for scripts and the settings UI
+#[derive(Debug, Clone, Serialize)]
+struct StatusReport {
+    ccusage_available: bool,
+    last_fetch_ts: Option<chrono::DateTime<chrono::Local>>,
+    last_error: Option<FetchError>,
+    stale: bool,
+    active_source: Option<&'static str>,
+    schema_used: Option<Schema>,
+    fetch_latency_ms: Option<u64>,
+}
+
+// Stale means at least one scheduled refresh was missed
+fn status_report(
+    cache: &SessionData,
+    last_fetch: Option<&FetchRecord>,
+    refresh_interval: Duration,
+) -> StatusReport {
+    StatusReport {
+        ccusage_available: cache.ccusage_available,
+        last_fetch_ts: last_fetch.map(|record| record.timestamp),
+        last_error: cache.last_error.clone(),
+        // Never having fetched counts as stale too
+        stale: cache
+            .last_updated
+            .map_or(true, |updated| updated.elapsed() > refresh_interval * 2),
+        active_source: cache.data_source.map(|source| source.command),
+        schema_used: cache.data_source.map(|source| source.schema),
+        fetch_latency_ms: cache.last_fetch_latency.map(|latency| latency.as_millis() as u64),
+    }
+}
+
+// Reads only what's cached; never runs ccusage
+#[tauri::command]
+fn get_status() -> StatusReport {
+    let last_fetch = FETCH_HISTORY.lock().unwrap().back().cloned();
+    let cache = SESSION_CACHE.lock().unwrap();
+    status_report(&cache, last_fetch.as_ref(), refresh_period(&settings::current(), &cache))
+}
+
+fn format_model_name(model_name: &str) -> String {
+    match model_name {
+        "claude-opus-4-20250514" => "Opus 4".to_string(),
+        "claude-sonnet-4-20250514" => "Sonnet 4".to_string(),
+        "claude-3-5-sonnet-20241022" => "Sonnet 3.5".to_string(),
+        "claude-3-haiku-20240307" => "Haiku".to_string(),
+        "gpt-5-codex" => "GPT-5 Codex".to_string(),
+        "gpt-5" => "GPT-5".to_string(),
+        _ => {
+            if model_name.contains("opus") {
+                "Opus".to_string()
+            } else if model_name.contains("sonnet") {
+                "Sonnet".to_string()
+            } else if model_name.contains("haiku") {
+                "Haiku".to_string()
+            } else if model_name.contains("gpt-5-codex") {
+                "GPT-5 Codex".to_string()
+            } else if model_name == "gpt-5" {
+                "GPT-5".to_string()
+            } else {
+                model_name.to_string()
+            }
+        }
+    }
+}
+
+/// Which JSON shape a response was parsed as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Schema {
+    Daily,
+    Sessions,
+    Blocks,
+    Single,
+    Array,
+}
+
+impl Schema {
+    fn label(&self) -> &'static str {
+        match self {
+            Schema::Daily => "daily",
+            Schema::Sessions => "sessions",
+            Schema::Blocks => "blocks",
+            Schema::Single => "single block",
+            Schema::Array => "block array",
+        }
+    }
+
+    // The daily view aggregates whole days: no block start/end times and no
+    // split between cache creation and cache reads
+    fn has_session_times(&self) -> bool {
+        *self != Schema::Daily
+    }
+
+    fn splits_cache_tokens(&self) -> bool {
+        *self != Schema::Daily
+    }
+}
+
+/// How the displayed data was obtained, for debugging "wrong numbers" reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+struct DataSource {
+    schema: Schema,
+    command: &'static str,
+}
+
+impl DataSource {
+    fn describe(&self) -> String {
+        format!("{} via {}", self.schema.label(), self.command)
+    }
+}
+
+/// Why no command produced data, kept for the menu and status
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum FetchError {
+    /// No ccusage, npx or shell to run
+    CommandNotFound,
+    /// The command outlived the CLI timeout and was killed
+    Timeout,
+    /// The command failed; first line of what it printed to stderr
+    NonZeroExit { stderr: String },
+    /// Nothing but whitespace on stdout
+    EmptyOutput,
+    /// Output shaped like a known schema that still didn't parse
+    ParseError { schema: Schema },
+    /// Output that none of the known schemas accept
+    SchemaUnknown,
+}
+
+impl FetchError {
+    fn message(&self) -> String {
+        match self {
+            FetchError::CommandNotFound => "ccusage not found".to_string(),
+            FetchError::Timeout => "ccusage timed out".to_string(),
+            FetchError::NonZeroExit { stderr } if stderr.is_empty() => "ccusage failed".to_string(),
+            FetchError::NonZeroExit { stderr } => format!("ccusage failed: {}", stderr),
+            FetchError::EmptyOutput => "ccusage returned no output".to_string(),
+            FetchError::ParseError { schema } => format!("Couldn't read ccusage {} output", schema.label()),
+            FetchError::SchemaUnknown => "ccusage output has an unknown format".to_string(),
+        }
+    }
+
+    // Whether a newer ccusage may have changed its output
+    fn is_format_change(&self) -> bool {
+        matches!(self, FetchError::ParseError { .. } | FetchError::SchemaUnknown)
+    }
+}
+
+// Menu-sized excerpt of a failed command's stderr: its first non-blank line
+fn stderr_summary(stderr: &[u8]) -> String {
+    const MAX_CHARS: usize = 80;
+    let stderr = String::from_utf8_lossy(stderr);
+    let line = stderr.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+    if line.chars().count() > MAX_CHARS {
+        format!("{}…", line.chars().take(MAX_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+// Reject blank output up front rather than running every schema parse on it
+fn non_empty_output(stdout: &str) -> Result<&str, FetchError> {
+    if stdout.trim().is_empty() {
+        Err(FetchError::EmptyOutput)
+    } else {
+        Ok(stdout)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct FetchResult {
+    active_block: Option<BlockData>,
+    // Full daily list when the daily schema answered; empty otherwise
+    daily: Vec<DailyEntry>,
+    // Figures across everything the daily view returned
+    totals: Option<Totals>,
+    ccusage_available: bool,
+    source: Option<DataSource>,
+    // Last thing that went wrong when no command produced data
+    error: Option<FetchError>,
+    // Each tool's own figures when both were combined
+    per_source: Vec<(CliTool, BlockData)>,
+}
+
+impl FetchResult {
+    fn found(active_block: Option<BlockData>, source: DataSource) -> Self {
+        FetchResult {
+            active_block,
+            ccusage_available: true,
+            source: Some(source),
+            ..Default::default()
+        }
+    }
+}
+
+const NVM_SOURCE: &str = r#"NVM_DIR="${NVM_DIR:-$HOME/.nvm}"; [ -s "$NVM_DIR/nvm.sh" ] && . "$NVM_DIR/nvm.sh""#;
+
+/// Which ccusage CLI a command runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CliTool {
+    Codex,
+    Claude,
+}
+
+impl CliTool {
+    fn label(&self) -> &'static str {
+        match self {
+            CliTool::Codex => "Codex (@ccusage/codex)",
+            CliTool::Claude => "Claude Code (ccusage)",
+        }
+    }
+
+    fn short_label(&self) -> &'static str {
+        match self {
+            CliTool::Codex => "Codex",
+            CliTool::Claude => "Claude Code",
+        }
+    }
+}
+
+struct CliCommand {
+    // Short description shown in debug output and used to remember winners.
+    // Lists may share a label only for an identical command line, so a
+    // winner remembered from one list means the same run in another.
+    label: &'static str,
+    program: &'static str,
+    args: Vec<String>,
+}
+
+fn login_zsh_command(label: &'static str, command: String) -> CliCommand {
+    CliCommand {
+        label,
+        program: "/bin/zsh",
+        args: vec!["-l".to_string(), "-c".to_string(), format!("{}; {}", NVM_SOURCE, command)],
+    }
+}
+
+fn sh_command(label: &'static str, command: String) -> CliCommand {
+    CliCommand {
+        label,
+        program: "sh",
+        args: vec!["-c".to_string(), command],
+    }
+}
+
+// Newest @ccusage/codex whose output this build is known to parse. Offered as a
+// pin when @latest changes its format; bump it after checking a new release.
+const LAST_KNOWN_COMPATIBLE_VERSION: &str = "17.1.0";
+
+// Package spec honouring the pinned version, e.g. "@ccusage/codex@latest"
+fn codex_package() -> String {
+    format!("@ccusage/codex@{}", settings::current().ccusage_version())
+}
+
+// Ways to run ccusage with the given subcommand (e.g. "daily --json"), in the order tried.
+// Use login zsh so ~/.zprofile (Homebrew path, etc.) is loaded; avoid interactive ~/.zshrc
+fn ccusage_commands(subcommand: &str) -> Vec<CliCommand> {
+    let package = codex_package();
+    vec![
+        login_zsh_command(
+            "login-zsh npm exec",
+            format!("npm exec --yes {} -- {}", package, subcommand),
+        ),
+        login_zsh_command("login-zsh npx", format!("npx {} {}", package, subcommand)),
+        login_zsh_command("login-zsh ccusage", format!("ccusage {}", subcommand)),
+        // Fallbacks without login shell
+        sh_command("sh ccusage", format!("ccusage {}", subcommand)),
+        sh_command("sh npx", format!("npx {} {}", package, subcommand)),
+    ]
+}
+
+// Commands that only ever run the given tool, for explicit or auto-detected sources
+fn tool_commands(tool: CliTool, subcommand: &str) -> Vec<CliCommand> {
+    match tool {
+        CliTool::Codex => {
+            let package = codex_package();
+            vec![
+                login_zsh_command(
+                    "login-zsh npm exec",
+                    format!("npm exec --yes {} -- {}", package, subcommand),
+                ),
+                login_zsh_command("login-zsh npx", format!("npx {} {}", package, subcommand)),
+                sh_command("sh npx", format!("npx {} {}", package, subcommand)),
+            ]
+        }
+        CliTool::Claude => vec![
+            login_zsh_command("login-zsh ccusage", format!("ccusage {}", subcommand)),
+            login_zsh_command("login-zsh npx ccusage", format!("npx ccusage@latest {}", subcommand)),
+            sh_command("sh ccusage", format!("ccusage {}", subcommand)),
+        ],
+    }
+}
+
+// Extra environment for ccusage runs from the registry and proxy settings.
+// Proxies go in both cases since npm and node tooling disagree on which they read.
+fn fetch_env(settings: &Settings) -> Vec<(&'static str, String)> {
+    let mut env = Vec::new();
+    let mut set = |names: &[&'static str], value: &Option<String>| {
+        if let Some(value) = value.as_deref().map(str::trim).filter(|value| !value.is_empty()) {
+            for name in names {
+                env.push((*name, value.to_string()));
+            }
+        }
+    };
+    set(&["npm_config_registry"], &settings.npm_registry);
+    set(&["HTTPS_PROXY", "https_proxy"], &settings.https_proxy);
+    set(&["HTTP_PROXY", "http_proxy"], &settings.http_proxy);
+    set(&["NO_PROXY", "no_proxy"], &settings.no_proxy);
+    // ccusage buckets days in the process timezone
+    if settings.day_timezone == DayTimezone::Utc {
+        env.push(("TZ", "UTC".to_string()));
+    }
+    env
+}
+
+// Tools that answered the last probe (auto-detect fetch or environment check)
+static DETECTED_TOOLS: Mutex<Vec<CliTool>> = Mutex::new(Vec::new());
+
+// The active block; once the session has ended, today's finished blocks summed
+// into one inactive block so the menu doesn't claim there was no usage
+fn active_or_today(blocks: Vec<BlockData>, today: chrono::NaiveDate, timezone: DayTimezone) -> Option<BlockData> {
+    if let Some(active) = blocks.iter().find(|block| block.is_active) {
+        return Some(active.clone());
+    }
+    let started_today = |block: &&BlockData| {
+        chrono::DateTime::parse_from_rfc3339(&block.start_time)
+            .is_ok_and(|start| timezone.date_of(&start) == today)
+    };
+    let todays: Vec<&BlockData> = blocks.iter().filter(started_today).collect();
+    if todays.is_empty() {
+        return None;
+    }
+
+    let mut models: Vec<String> = todays.iter().flat_map(|block| block.models.clone()).collect();
+    models.sort();
+    models.dedup();
+    let costs: Vec<f64> = todays.iter().filter_map(|block| block.cost_usd).collect();
+    let sum_tokens = |field: fn(&TokenCounts) -> u64| -> u64 {
+        todays.iter().map(|block| field(&block.token_counts)).sum()
+    };
+    Some(BlockData {
+        id: format!("today-{}", today),
+        // A span of finished sessions has no single start or expiry
+        start_time: String::new(),
+        end_time: String::new(),
+        is_active: false,
+        token_counts: TokenCounts {
+            input_tokens: sum_tokens(|counts| counts.input_tokens),
+            output_tokens: sum_tokens(|counts| counts.output_tokens),
+            cache_creation_input_tokens: sum_tokens(|counts| counts.cache_creation_input_tokens),
+            cache_read_input_tokens: sum_tokens(|counts| counts.cache_read_input_tokens),
+        },
+        cost_usd: (!costs.is_empty()).then(|| costs.iter().sum()),
+        models,
+        model_stats: HashMap::new(),
+        token_limit_status: None,
+    })
+}
+
+// Block-shaped schemas (sessions, blocks, a single block, a bare array).
+// None means stdout matched none of them.
+fn parse_block_schemas(stdout: &str) -> Option<(Schema, Option<BlockData>)> {
+    let timezone = settings::current().day_timezone;
+    let today = timezone.date_of(&chrono::Local::now());
+    if let Ok(response) = serde_json::from_str::<SessionsResponse>(stdout) {
+        return Some((Schema::Sessions, active_or_today(response.sessions, today, timezone)));
+    }
+    if let Ok(response) = serde_json::from_str::<BlocksResponse>(stdout) {
+        return Some((Schema::Blocks, active_or_today(response.blocks, today, timezone)));
+    }
+    if let Ok(block) = serde_json::from_str::<BlockData>(stdout) {
+        return Some((Schema::Single, Some(block)));
+    }
+    if let Ok(blocks) = serde_json::from_str::<Vec<BlockData>>(stdout) {
+        return Some((Schema::Array, active_or_today(blocks, today, timezone)));
+    }
+    None
+}
+
+// Raw stdout of the last command that exited successfully, parsed or not,
+// capped so a huge history can't bloat memory
+const RAW_OUTPUT_LIMIT: usize = 256 * 1024;
+static LAST_RAW_OUTPUT: Mutex<Option<String>> = Mutex::new(None);
+
+fn remember_raw_output(stdout: &str) {
+    let raw = if stdout.len() > RAW_OUTPUT_LIMIT {
+        let mut end = RAW_OUTPUT_LIMIT;
+        while !stdout.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{}\n... (truncated, {} bytes total)", &stdout[..end], stdout.len())
+    } else {
+        stdout.to_string()
+    };
+    *LAST_RAW_OUTPUT.lock().unwrap() = Some(raw);
+}
+
+// Labels of the commands that last produced data, at most one per command
+// list. They are tried first so a working setup doesn't pay for earlier failures.
+static WINNING_COMMANDS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+// Forget the remembered commands so the next fetch walks the full list again,
+// e.g. after switching node managers
+fn forget_winning_command() {
+    WINNING_COMMANDS.lock().unwrap().clear();
+}
+
+fn remember_winning_command(commands: &[CliCommand], winner: &'static str) {
+    let mut winners = WINNING_COMMANDS.lock().unwrap();
+    winners.retain(|label| !commands.iter().any(|command| command.label == *label));
+    winners.push(winner);
+}
+
+// The shell line for a `ccusage_command` override: verbatim in exact mode,
+// otherwise with `subcommand` (the daily report, or the block view) appended
+fn custom_daily_command(command: &str, exact: bool, subcommand: &str) -> String {
+    if exact {
+        command.to_string()
+    } else {
+        format!("{} {}", command, subcommand)
+    }
+}
+
+async fn fetch_session_data() -> FetchResult {
+    let subcommand = "daily --json";
+    let settings = settings::current();
+    if let Some(command) = settings.ccusage_command() {
+        return fetch_with_commands(vec![login_zsh_command(
+            "custom command",
+            custom_daily_command(command, settings.ccusage_command_exact, subcommand),
+        )])
+        .await;
+    }
+    if settings.read_session_logs {
+        if let Some(result) = fetch_from_logs(&settings).await {
+            return result;
+        }
+    }
+    match settings.tool_preference {
+        ToolPreference::Codex => fetch_with_commands(ccusage_commands(subcommand)).await,
+        ToolPreference::Claude => {
+            fetch_with_commands(tool_commands(CliTool::Claude, subcommand)).await
+        }
+        preference @ (ToolPreference::Auto | ToolPreference::Both) => {
+            let (codex, claude) = tokio::join!(
+                fetch_with_commands(tool_commands(CliTool::Codex, subcommand)),
+                fetch_with_commands(tool_commands(CliTool::Claude, subcommand)),
+            );
+            let mut detected = Vec::new();
+            if codex.ccusage_available {
+                detected.push(CliTool::Codex);
+            }
+            if claude.ccusage_available {
+                detected.push(CliTool::Claude);
+            }
+            *DETECTED_TOOLS.lock().unwrap() = detected;
+            if preference == ToolPreference::Both {
+                combine_results(codex, claude)
+            } else {
+                pick_auto_result(codex, claude)
+            }
+        }
+    }
+}
+
+fn add_model_stats(into: &mut ModelStats, other: &ModelStats) {
+    let add = |a: Option<u64>, b: Option<u64>| a.zip(b).map(|(a, b)| a + b).or(a).or(b);
+    into.input_tokens = add(into.input_tokens, other.input_tokens);
+    into.cached_input_tokens = add(into.cached_input_tokens, other.cached_input_tokens);
+    into.output_tokens = add(into.output_tokens, other.output_tokens);
+    into.total_tokens = add(into.total_tokens, other.total_tokens);
+    into.cost_usd = into.cost_usd.zip(other.cost_usd).map(|(a, b)| a + b).or(into.cost_usd).or(other.cost_usd);
+}
+
+// One block summing both tools' days. Cost is the sum of those that reported
+// one, None only when neither did.
+fn merge_blocks(blocks: &[&BlockData]) -> BlockData {
+    let mut merged = BlockData {
+        id: "combined".to_string(),
+        start_time: String::new(),
+        end_time: String::new(),
+        is_active: blocks.iter().any(|block| block.is_active),
+        token_counts: TokenCounts::default(),
+        cost_usd: None,
+        models: Vec::new(),
+        model_stats: HashMap::new(),
+        token_limit_status: None,
+    };
+    for block in blocks {
+        let counts = &block.token_counts;
+        merged.token_counts.input_tokens += counts.input_tokens;
+        merged.token_counts.output_tokens += counts.output_tokens;
+        merged.token_counts.cache_creation_input_tokens += counts.cache_creation_input_tokens;
+        merged.token_counts.cache_read_input_tokens += counts.cache_read_input_tokens;
+        if let Some(cost) = block.cost_usd {
+            *merged.cost_usd.get_or_insert(0.0) += cost;
+        }
+        merged.models.extend(block.models.iter().cloned());
+        for (model, stats) in &block.model_stats {
+            add_model_stats(merged.model_stats.entry(model.clone()).or_default(), stats);
+        }
+    }
+    merged.models.sort();
+    merged.models.dedup();
+    merged
+}
+
+// Both tools' daily lists summed per day, oldest first. The tools may date
+// entries differently, so days are matched on the parsed date.
+fn merge_daily(codex: &[DailyEntry], claude: &[DailyEntry]) -> Vec<DailyEntry> {
+    let mut days: std::collections::BTreeMap<chrono::NaiveDate, DailyEntry> = std::collections::BTreeMap::new();
+    for entry in codex.iter().chain(claude) {
+        let Some(date) = parse_daily_date(&entry.date) else {
+            continue;
+        };
+        let Some(day) = days.get_mut(&date) else {
+            days.insert(date, DailyEntry { date: date.format("%Y-%m-%d").to_string(), ..entry.clone() });
+            continue;
+        };
+        day.input_tokens += entry.input_tokens;
+        day.cached_input_tokens += entry.cached_input_tokens;
+        day.output_tokens += entry.output_tokens;
+        day.total_tokens += entry.total_tokens;
+        day.cost_usd = day.cost_usd.zip(entry.cost_usd).map(|(a, b)| a + b).or(day.cost_usd).or(entry.cost_usd);
+        for (model, stats) in &entry.models {
+            add_model_stats(day.models.entry(model.clone()).or_default(), stats);
+        }
+    }
+    days.into_values().collect()
+}
+
+// Multi-source mode: the two tools added together, each one's block kept for
+// its own menu line. With only one tool answering, that one is the result.
+fn combine_results(codex: FetchResult, claude: FetchResult) -> FetchResult {
+    let per_source: Vec<(CliTool, BlockData)> = [(CliTool::Codex, &codex), (CliTool::Claude, &claude)]
+        .into_iter()
+        .filter_map(|(tool, result)| Some((tool, result.active_block.clone()?)))
+        .collect();
+    let mut combined = match (codex.ccusage_available, claude.ccusage_available) {
+        (true, true) => {
+            let blocks: Vec<&BlockData> = per_source.iter().map(|(_, block)| block).collect();
+            let totals = match (codex.totals, claude.totals) {
+                (Some(a), Some(b)) => Some(Totals {
+                    cost_usd: a.cost_usd + b.cost_usd,
+                    input_tokens: a.input_tokens + b.input_tokens,
+                    output_tokens: a.output_tokens + b.output_tokens,
+                    total_tokens: a.total_tokens + b.total_tokens,
+                }),
+                (a, b) => a.or(b),
+            };
+            FetchResult {
+                active_block: (!blocks.is_empty()).then(|| merge_blocks(&blocks)),
+                daily: merge_daily(&codex.daily, &claude.daily),
+                totals,
+                ccusage_available: true,
+                source: codex.source,
+                ..Default::default()
+            }
+        }
+        (false, true) => claude,
+        _ => codex,
+    };
+    combined.per_source = per_source;
+    combined
+}
+
+// The daily view built from the session logs in-process; None hands the fetch
+// to ccusage
+async fn fetch_from_logs(settings: &Settings) -> Option<FetchResult> {
+    let tools = match settings.tool_preference {
+        ToolPreference::Codex => vec![CliTool::Codex],
+        ToolPreference::Claude => vec![CliTool::Claude],
+        ToolPreference::Auto | ToolPreference::Both => vec![CliTool::Codex, CliTool::Claude],
+    };
+    let timezone = settings.day_timezone;
+    let prices = settings.model_prices.clone();
+    let max_days = settings.max_cached_days;
+    let daily = tauri::async_runtime::spawn_blocking(move || {
+        native::read_daily(&tools, timezone, &prices, max_days)
+    })
+    .await
+    .ok()??;
+    let entry = today_entry(&daily, timezone.date_of(&chrono::Local::now()));
+    Some(FetchResult {
+        active_block: Some(daily_to_block(&entry)),
+        totals: Some(resolve_totals(None, &daily)),
+        daily,
+        ccusage_available: true,
+        source: Some(DataSource {
+            schema: Schema::Daily,
+            command: "session logs",
+        }),
+        ..Default::default()
+    })
+}
+
+// Whichever tool has spend today wins; Codex breaks ties, matching the default
+fn pick_auto_result(codex: FetchResult, claude: FetchResult) -> FetchResult {
+    let today_cost = |result: &FetchResult| {
+        result
+            .active_block
+            .as_ref()
+            .and_then(|block| block.cost_usd)
+            .unwrap_or(0.0)
+    };
+    if claude.ccusage_available
+        && (!codex.ccusage_available || today_cost(&claude) > today_cost(&codex))
+    {
+        claude
+    } else {
+        codex
+    }
+}
+
+// Spawns that fail with EAGAIN (process table momentarily full) or an interrupted
+// syscall usually succeed moments later; anything else, e.g. not found, won't
+const SPAWN_RETRIES: u32 = 2;
+const SPAWN_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+fn is_transient_spawn_error(error: &std::io::Error) -> bool {
+    matches!(
+        error.kind(),
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted
+    )
+}
+
+// Run one ccusage command, killing it once it outlives `limit`. A timeout
+// comes back as a TimedOut error so callers move on to the next fallback.
+async fn run_cli(
+    command: &CliCommand,
+    env: &[(&'static str, String)],
+    limit: Duration,
+) -> std::io::Result<std::process::Output> {
+    let output = Command::new(command.program)
+        .args(&command.args)
+        .envs(env.iter().cloned())
+        .kill_on_drop(true)
+        .output();
+    tokio::time::timeout(limit, output).await.unwrap_or_else(|_| {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("'{}' timed out after {}s", command.label, limit.as_secs()),
+        ))
+    })
+}
+
+async fn fetch_with_commands(shell_commands: Vec<CliCommand>) -> FetchResult {
+    // Try multiple approaches to find and run CLI, remembered winner first
+    let mut order: Vec<usize> = (0..shell_commands.len()).collect();
+    {
+        let winners = WINNING_COMMANDS.lock().unwrap();
+        if let Some(position) = order
+            .iter()
+            .position(|&index| winners.contains(&shell_commands[index].label))
+        {
+            let winner = order.remove(position);
+            order.insert(0, winner);
+        }
+    }
+
+    let settings = settings::current();
+    let env = fetch_env(&settings);
+    let mut last_error = None;
+    for index in order {
+        let command = &shell_commands[index];
+        let mut attempt = 0;
+        let output = loop {
+            let output = run_cli(command, &env, settings.cli_timeout()).await;
+            match &output {
+                Err(e) if is_transient_spawn_error(e) && attempt < SPAWN_RETRIES => {
+                    attempt += 1;
+                    eprintln!(
+                        "Transient spawn error for '{}' ({}); retry {}/{}",
+                        command.label, e, attempt, SPAWN_RETRIES
+                    );
+                    tokio::time::sleep(SPAWN_RETRY_DELAY).await;
+                }
+                _ => break output,
+            }
+        };
+
+        match output {
+            Ok(output) if output.status.success() => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                remember_raw_output(&stdout);
+                let stdout = match non_empty_output(&stdout) {
+                    Ok(stdout) => stdout,
+                    Err(error) => {
+                        eprintln!("{} ({})", error.message(), command.label);
+                        last_error = Some(error);
+                        continue;
+                    }
+                };
+
+                // Try to parse the response with multiple schemas for compatibility
+                if let Ok(response) = serde_json::from_str::<DailyResponse>(stdout) {
+                    // Prefer today's entry; if missing, show 0.00 for today
+                    let today = settings::current().day_timezone.date_of(&chrono::Local::now());
+                    let entry = today_entry(&response.daily, today);
+                    remember_winning_command(&shell_commands, command.label);
+                    return FetchResult {
+                        active_block: Some(daily_to_block(&entry)),
+                        totals: Some(resolve_totals(response.totals.as_ref(), &response.daily)),
+                        daily: response.daily,
+                        ccusage_available: true,
+                        source: Some(DataSource {
+                            schema: Schema::Daily,
+                            command: command.label,
+                        }),
+                        ..Default::default()
+                    };
+                }
+                if let Some((schema, active_block)) = parse_block_schemas(stdout) {
+                    remember_winning_command(&shell_commands, command.label);
+                    let source = DataSource {
+                        schema,
+                        command: command.label,
+                    };
+                    return FetchResult::found(active_block, source);
+                }
+
+                eprintln!("Failed to parse CLI response with known schemas");
+                eprintln!("Response was: {}", stdout);
+                // A "daily" key means the daily schema changed under us
+                let looks_daily = serde_json::from_str::<serde_json::Value>(stdout)
+                    .is_ok_and(|value| value.get("daily").is_some());
+                last_error = Some(if looks_daily {
+                    FetchError::ParseError { schema: Schema::Daily }
+                } else {
+                    FetchError::SchemaUnknown
+                });
+                continue;
+            }
+            Ok(output) => {
+                eprintln!("ccusage command failed with status: {}", output.status);
+                eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+                last_error = Some(FetchError::NonZeroExit {
+                    stderr: stderr_summary(&output.stderr),
+                });
+                continue;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                eprintln!("{}; trying the next fallback", e);
+                last_error = Some(FetchError::Timeout);
+                continue;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!("'{}' not found; trying the next fallback", command.program);
+                // Anything a command that did run reported says more
+                last_error.get_or_insert(FetchError::CommandNotFound);
+                continue;
+            }
+            Err(e) => {
+                eprintln!("Failed to execute command '{}': {}", command.program, e);
+                continue;
+            }
+        }
+    }
+
+    eprintln!("All attempts to fetch session data failed");
+    FetchResult {
+        error: last_error,
+        ..Default::default()
+    }
+}
+
+/// `ccusage daily --instances --json`: the usual daily entries, grouped by the
+/// project directory they were logged under
+#[derive(Debug, Deserialize)]
+struct ProjectsResponse {
+    projects: HashMap<String, Vec<ProjectDay>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProjectDay {
+    #[serde(default, deserialize_with = "deserialize_null_as_empty")]
+    date: String,
+    #[serde(rename = "totalCost", alias = "costUSD", default)]
+    cost_usd: f64,
+}
+
+// Claude Code names project folders after their path with separators turned
+// into dashes ("-Users-me-src-app"); drop the home part so the repo shows
+fn project_label(name: &str) -> String {
+    if let Some(last) = name.rsplit('/').next().filter(|_| name.contains('/')) {
+        return last.to_string();
+    }
+    let home = dirs::home_dir()
+        .map(|home| home.to_string_lossy().replace(['/', '.'], "-"))
+        .unwrap_or_default();
+    name.strip_prefix(&home)
+        .filter(|rest| !home.is_empty() && !rest.is_empty())
+        .unwrap_or(name)
+        .trim_start_matches('-')
+        .to_string()
+}
+
+// Projects with spend on `today`, most expensive first, at most `limit`
+fn top_projects(response: ProjectsResponse, today: chrono::NaiveDate, limit: usize) -> Vec<(String, f64)> {
+    let mut projects: Vec<(String, f64)> = response
+        .projects
+        .into_iter()
+        .map(|(name, days)| {
+            let cost = days
+                .iter()
+                .filter(|day| parse_daily_date(&day.date) == Some(today))
+                .map(|day| day.cost_usd)
+                .sum();
+            (project_label(&name), cost)
+        })
+        .filter(|(_, cost)| *cost > 0.0)
+        .collect();
+    projects.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    projects.truncate(limit);
+    projects
+}
+
+// Per-project costs only come from the Claude Code ccusage; @ccusage/codex
+// has no --instances
+async fn fetch_projects(today: chrono::NaiveDate, limit: usize) -> Vec<(String, f64)> {
+    let settings = settings::current();
+    let (env, timeout) = (fetch_env(&settings), settings.cli_timeout());
+    let subcommand = format!("daily --instances --json --since {}", today.format("%Y%m%d"));
+    for command in tool_commands(CliTool::Claude, &subcommand) {
+        match run_cli(&command, &env, timeout).await {
+            Ok(output) if output.status.success() => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                if let Ok(response) = serde_json::from_str::<ProjectsResponse>(&stdout) {
+                    return top_projects(response, today, limit);
+                }
+            }
+            Ok(_) | Err(_) => continue,
+        }
+    }
+    Vec::new()
+}
+
+// Tools whose blocks to ask for, chosen like the daily fetch: the preferred
+// one, or for Auto/Both those detected on the last refresh (both until then)
+fn block_tools(preference: ToolPreference, detected: &[CliTool]) -> Vec<CliTool> {
+    match preference {
+        ToolPreference::Codex => vec![CliTool::Codex],
+        ToolPreference::Claude => vec![CliTool::Claude],
+        ToolPreference::Auto | ToolPreference::Both if detected.is_empty() => vec![CliTool::Codex, CliTool::Claude],
+        ToolPreference::Auto | ToolPreference::Both => detected.to_vec(),
+    }
+}
+
+// Each tool keeps its own 5-hour windows, so with both answering the menu
+// shows one: an active block over a finished one, then the costlier, with
+// Codex breaking ties like pick_auto_result
+fn pick_block(codex: Option<BlockData>, claude: Option<BlockData>) -> Option<BlockData> {
+    match (codex, claude) {
+        (Some(codex), Some(claude)) => {
+            let rank = |block: &BlockData| (block.is_active, block.cost_usd.unwrap_or(0.0));
+            let ((codex_active, codex_cost), (claude_active, claude_cost)) = (rank(&codex), rank(&claude));
+            if (claude_active && !codex_active) || (claude_active == codex_active && claude_cost > codex_cost) {
+                Some(claude)
+            } else {
+                Some(codex)
+            }
+        }
+        (codex, claude) => codex.or(claude),
+    }
+}
+
+// The first block-shaped answer from `commands`, tried in order
+async fn fetch_block_with(
+    commands: Vec<CliCommand>,
+    env: Vec<(&'static str, String)>,
+    timeout: Duration,
+) -> Option<BlockData> {
+    for command in commands {
+        match run_cli(&command, &env, timeout).await {
+            Ok(output) if output.status.success() => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let Ok(stdout) = non_empty_output(&stdout) else {
+                    continue;
+                };
+                if let Some((_, active_block)) = parse_block_schemas(stdout) {
+                    return active_block;
+                }
+            }
+            Ok(_) | Err(_) => continue,
+        }
+    }
+    None
+}
+
+// The 5-hour block view, fetched alongside the daily view when enabled.
+// Not every ccusage flavour has a blocks command, so failures just yield None.
+async fn fetch_active_block() -> Option<BlockData> {
+    let settings = settings::current();
+    let (env, timeout) = (fetch_env(&settings), settings.cli_timeout());
+    let subcommand = match settings.block_token_limit {
+        Some(limit) => format!("blocks --active --json --token-limit {}", limit.cli_arg()),
+        None => "blocks --active --json".to_string(),
+    };
+    if let Some(command) = settings.ccusage_command() {
+        // An exact command is the daily report alone; there's no block view to ask it for
+        if settings.ccusage_command_exact {
+            return None;
+        }
+        let command = login_zsh_command("custom command", custom_daily_command(command, false, &subcommand));
+        return fetch_block_with(vec![command], env, timeout).await;
+    }
+    let preference = settings.tool_preference;
+    let tools = block_tools(preference, &DETECTED_TOOLS.lock().unwrap());
+    let fetch = |tool: CliTool| {
+        let wanted = tools.contains(&tool);
+        // The same lists the daily fetch walks for this preference
+        let commands = match preference {
+            ToolPreference::Codex => ccusage_commands(&subcommand),
+            _ => tool_commands(tool, &subcommand),
+        };
+        let env = env.clone();
+        async move {
+            if wanted {
+                fetch_block_with(commands, env, timeout).await
+            } else {
+                None
+            }
+        }
+    };
+    let (codex, claude) = tokio::join!(fetch(CliTool::Codex), fetch(CliTool::Claude));
+    pick_block(codex, claude)
+}
+
+// Whether npx has already fetched @ccusage/codex into its cache
+fn codex_in_npx_cache() -> bool {
+    let Some(npx_dir) = dirs::home_dir().map(|home| home.join(".npm").join("_npx")) else {
+        return false;
+    };
+    std::fs::read_dir(npx_dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.path().join("node_modules/@ccusage/codex").exists())
+        })
+        .unwrap_or(false)
+}
+
+// Node is installed but neither a global ccusage nor a cached @ccusage/codex
+// is, so the first fetch will download it
+async fn first_use_install_pending() -> bool {
+    if settings::current().tool_preference == ToolPreference::Claude || codex_in_npx_cache() {
+        return false;
+    }
+    let env = fetch_env(&settings::current());
+    let (node, ccusage) = tokio::join!(
+        run_probe("which node".to_string(), env.clone()),
+        run_probe("which ccusage".to_string(), env),
+    );
+    let found = |probe: Result<std::process::Output, String>| probe.is_ok_and(|output| output.status.success());
+    found(node) && !found(ccusage)
+}
+
+// Per-probe limit, generous because npx may download the package first, and a
+// cap on the whole check so a hung probe can't hold up the dialog
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+const ENV_CHECK_TIMEOUT: Duration = Duration::from_secs(20);
+
+// One environment check in login zsh with nvm sourced; the shell is killed if
+// it outlives PROBE_TIMEOUT
+async fn run_probe(
+    script: String,
+    env: Vec<(&'static str, String)>,
+) -> Result<std::process::Output, String> {
+    let cmd_with_nvm = format!("{}; {}", NVM_SOURCE, script);
+    let output = Command::new("/bin/zsh")
+        .args(["-l", "-c", &cmd_with_nvm])
+        .envs(env)
+        .kill_on_drop(true)
+        .output();
+    match tokio::time::timeout(PROBE_TIMEOUT, output).await {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err(format!("timed out after {}s", PROBE_TIMEOUT.as_secs())),
+    }
+}
+
+async fn get_debug_info() -> String {
+    let mut debug_info = String::new();
+    
+    // Get PATH environment variable
+    debug_info.push_str("Environment:\n");
+    if let Ok(path) = std::env::var("PATH") {
+        debug_info.push_str(&format!("Default PATH: {}\n", path));
+    } else {
+        debug_info.push_str("Default PATH: (not set)\n");
+    }
+    
+    // Registry and proxies the ccusage runs actually see
+    let current_settings = settings::current();
+    let env = fetch_env(&current_settings);
+    let effective = |configured: &Option<String>, names: &[&str], fallback: &str| {
+        configured
+            .clone()
+            .filter(|value| !value.trim().is_empty())
+            .or_else(|| names.iter().find_map(|name| std::env::var(name).ok()))
+            .unwrap_or_else(|| fallback.to_string())
+    };
+    debug_info.push_str(&format!(
+        "npm registry: {}\n",
+        effective(&current_settings.npm_registry, &["npm_config_registry"], "(npm default)")
+    ));
+    debug_info.push_str(&format!(
+        "HTTPS proxy: {}\n",
+        effective(&current_settings.https_proxy, &["HTTPS_PROXY", "https_proxy"], "(none)")
+    ));
+    debug_info.push_str(&format!(
+        "HTTP proxy: {}\n",
+        effective(&current_settings.http_proxy, &["HTTP_PROXY", "http_proxy"], "(none)")
+    ));
+
+    // Explain shell used for checks
+    debug_info.push_str("Checks run in login zsh; nvm sourced if present\n\n");
+    
+    // Test commands with login zsh + optional nvm sourcing
+    debug_info.push_str("Command availability (login zsh + nvm):\n");
+    
+    let commands_to_test = vec![
+        ("which npx".to_string(), "npx location"),
+        ("which node".to_string(), "node location"),
+        ("which ccusage".to_string(), "ccusage location"),
+        ("npx --version".to_string(), "npx version"),
+        ("node --version".to_string(), "node version"),
+        ("ccusage --version 2>&1 || echo 'not found'".to_string(), "ccusage version"),
+    ];
+    let package = codex_package();
+    let ccusage_cmd = format!("npm exec --yes {} -- --version || npx {} --version", package, package);
+
+    // The probes are independent, so run them together; the last one tests @ccusage/codex
+    let mut scripts: Vec<String> = commands_to_test.iter().map(|(cmd, _)| cmd.clone()).collect();
+    scripts.push(ccusage_cmd);
+    let mut results: Vec<Option<Result<std::process::Output, String>>> =
+        scripts.iter().map(|_| None).collect();
+    let mut probes = tokio::task::JoinSet::new();
+    for (index, script) in scripts.into_iter().enumerate() {
+        let env = env.clone();
+        probes.spawn(async move { (index, run_probe(script, env).await) });
+    }
+    let collect = async {
+        while let Some(joined) = probes.join_next().await {
+            if let Ok((index, result)) = joined {
+                results[index] = Some(result);
+            }
+        }
+    };
+    if tokio::time::timeout(ENV_CHECK_TIMEOUT, collect).await.is_err() {
+        probes.abort_all();
+    }
+    let ccusage_output = results.pop().flatten();
+
+    let mut detected = Vec::new();
+    for ((_, desc), output) in commands_to_test.iter().zip(results) {
+        match output {
+            Some(Ok(output)) if output.status.success() => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                debug_info.push_str(&format!("{}: {}\n", desc, stdout.trim()));
+                if *desc == "ccusage location" {
+                    detected.push(CliTool::Claude);
+                }
+            }
+            Some(Ok(output)) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                if stderr.trim().is_empty() {
+                    debug_info.push_str(&format!("{}: not found\n", desc));
+                } else {
+                    debug_info.push_str(&format!("{}: {}\n", desc, stderr.trim()));
+                }
+            }
+            Some(Err(e)) => {
+                debug_info.push_str(&format!("{}: error - {}\n", desc, e));
+            }
+            None => {
+                debug_info.push_str(&format!("{}: no answer before the check timed out\n", desc));
+            }
+        }
+    }
+    
+    // Test @ccusage/codex with extended PATH
+    debug_info.push_str("\nTesting @ccusage/codex:\n");
+    match ccusage_output {
+        Some(Ok(output)) => {
+            if output.status.success() {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                debug_info.push_str(&format!("@ccusage/codex version: {}\n", stdout.trim()));
+                detected.insert(0, CliTool::Codex);
+            } else {
+                debug_info.push_str(&format!("@ccusage/codex: not available (npx {} failed)\n", package));
+                if !output.stderr.is_empty() {
+                    debug_info.push_str(&format!("Error: {}\n", String::from_utf8_lossy(&output.stderr).trim()));
+                }
+            }
+        }
+        Some(Err(e)) => {
+            debug_info.push_str(&format!("Error executing @ccusage/codex: {}\n", e));
+        }
+        None => {
+            debug_info.push_str("@ccusage/codex: no answer before the check timed out\n");
+        }
+    }
+    *DETECTED_TOOLS.lock().unwrap() = detected;
+
+    let (last_latency, avg_latency, data_source) = {
+        let cache = SESSION_CACHE.lock().unwrap();
+        (cache.last_fetch_latency, cache.avg_fetch_latency, cache.data_source)
+    };
+    match data_source {
+        Some(source) => debug_info.push_str(&format!("\nSource: {}\n", source.describe())),
+        None => debug_info.push_str("\nSource: (no successful fetch yet)\n"),
+    }
+    if let (Some(last), Some(avg)) = (last_latency, avg_latency) {
+        debug_info.push_str(&format!(
+            "\nLast fetch: {:.1}s (avg {:.1}s)\n",
+            last.as_secs_f64(),
+            avg.as_secs_f64()
+        ));
+        if last > SLOW_FETCH_THRESHOLD {
+            debug_info.push_str("Fetches are slow; pinning the @ccusage/codex version or a global install avoids npx resolving @latest each time\n");
+        }
+    }
+
+    // Recent fetch outcomes, newest first
+    debug_info.push_str("\nRecent Fetches:\n");
+    let history = get_recent_fetches();
+    if history.is_empty() {
+        debug_info.push_str("(none yet)\n");
+    }
+    for record in history.iter().rev() {
+        debug_info.push_str(&format!(
+            "{} {:?} {}ms\n",
+            record.timestamp.format("%H:%M:%S"),
+            record.outcome,
+            record.latency_ms
+        ));
+    }
+    
+    debug_info
+}
+
+// Models already alerted on today; cleared when the date changes
+static MODEL_ALERTS_SENT: Mutex<Option<(chrono::NaiveDate, HashSet<String>)>> = Mutex::new(None);
+
+// Per-model setting keyed by model id or display name (e.g. "Opus 4")
+fn model_setting<'a, T>(settings: &'a HashMap<String, T>, model: &str) -> Option<&'a T> {
+    let display_name = format_model_name(model);
+    settings
+        .iter()
+        .find(|(key, _)| key.as_str() == model || key.eq_ignore_ascii_case(&display_name))
+        .map(|(_, value)| value)
+}
+
+// Today's cost at the configured prices. None unless every model has both a
+// price and token counts, so a partial estimate never passes for the total.
+fn estimated_cost(block: &BlockData, prices: &HashMap<String, settings::ModelPrice>) -> Option<f64> {
+    if prices.is_empty() || block.model_stats.is_empty() {
+        return None;
+    }
+    block
+        .model_stats
+        .iter()
+        .map(|(model, stats)| {
+            let price = model_setting(prices, model)?;
+            Some(priced_cost(
+                price,
+                stats.input_tokens?,
+                stats.cached_input_tokens.unwrap_or(0),
+                stats.output_tokens?,
+            ))
+        })
+        .sum()
+}
+
+// USD for token counts at a per-million price
+fn priced_cost(price: &settings::ModelPrice, input: u64, cached_input: u64, output: u64) -> f64 {
+    let cost = |tokens: u64, usd_per_million: f64| tokens as f64 * usd_per_million / 1_000_000.0;
+    cost(input, price.input)
+        + cost(cached_input, price.cached_input.unwrap_or(price.input))
+        + cost(output, price.output)
+}
+
+// "Billed $3.21 · Est $3.40 · −$0.19", the delta being billed minus estimate
+fn billed_vs_estimate(billed: f64, estimate: f64) -> String {
+    let delta = billed - estimate;
+    let sign = if delta < -0.005 { "−" } else { "+" };
+    format!("Billed {} · Est {} · {}{}", money(billed), money(estimate), sign, money(delta.abs()))
+}
+
+fn check_model_budgets(block: &BlockData, budgets: &HashMap<String, f64>) {
+    if budgets.is_empty() || block.model_stats.is_empty() {
+        return;
+    }
+
+    let today = settings::current().day_timezone.date_of(&chrono::Local::now());
+    let mut sent = MODEL_ALERTS_SENT.lock().unwrap();
+    if sent.as_ref().map(|(date, _)| *date) != Some(today) {
+        *sent = Some((today, HashSet::new()));
+    }
+    let Some((_, alerted)) = sent.as_mut() else {
+        return;
+    };
+
+    for (model, stats) in &block.model_stats {
+        // Per-model costs are optional in the daily schema
+        let Some(cost) = stats.cost_usd else {
+            continue;
+        };
+        let Some(&budget) = model_setting(budgets, model) else {
+            continue;
+        };
+        let display_name = format_model_name(model);
+        if cost >= budget && alerted.insert(model.clone()) {
+            show_alert(
+                "CCUsage model budget",
+                &format!(
+                    "{} has cost ${:.2} today (budget ${:.2})",
+                    display_name, cost, budget
+                ),
+            );
+        }
+    }
+}
+
+// Daily cost thresholds already alerted on today; cleared when the date changes
+static THRESHOLD_ALERTS_SENT: Mutex<Option<(chrono::NaiveDate, Vec<f64>)>> = Mutex::new(None);
+
+// Thresholds `cost` has reached that haven't alerted yet, lowest first
+fn newly_crossed_thresholds(thresholds: &[f64], cost: f64, alerted: &[f64]) -> Vec<f64> {
+    let mut crossed: Vec<f64> = thresholds
+        .iter()
+        .copied()
+        .filter(|threshold| *threshold > 0.0 && cost >= *threshold && !alerted.contains(threshold))
+        .collect();
+    crossed.sort_by(f64::total_cmp);
+    crossed.dedup();
+    crossed
+}
+
+fn check_cost_thresholds(block: &BlockData, thresholds: &[f64]) {
+    let Some(cost) = block.cost_usd else {
+        return;
+    };
+    if thresholds.is_empty() {
+        return;
+    }
+
+    let today = settings::current().day_timezone.date_of(&chrono::Local::now());
+    let mut sent = THRESHOLD_ALERTS_SENT.lock().unwrap();
+    if sent.as_ref().map(|(date, _)| *date) != Some(today) {
+        *sent = Some((today, Vec::new()));
+    }
+    let Some((_, alerted)) = sent.as_mut() else {
+        return;
+    };
+
+    let crossed = newly_crossed_thresholds(thresholds, cost, alerted);
+    // Jumping past several at once only announces the highest
+    if let Some(&highest) = crossed.last() {
+        show_alert(
+            "CCUsage daily cost",
+            &format!("Today's cost reached ${:.2} (threshold ${:.2})", cost, highest),
+        );
+        alerted.extend(crossed);
+    }
+}
+
+// Share of the block's token limit at which to warn, in percent
+const TOKEN_LIMIT_WARNINGS: [f64; 2] = [80.0, 95.0];
+
+// Warnings already sent for the active block; cleared when a new block starts
+static TOKEN_LIMIT_ALERTS_SENT: Mutex<Option<(String, Vec<f64>)>> = Mutex::new(None);
+
+// Tokens the block has used and its limit: ccusage's own figure when it ran
+// with --token-limit, else a configured count
+fn block_token_usage(block: &BlockData, limit: Option<settings::TokenLimit>) -> Option<(u64, u64)> {
+    let limit = match (&block.token_limit_status, limit?) {
+        (Some(status), _) => status.limit,
+        (None, settings::TokenLimit::Tokens(tokens)) => tokens,
+        (None, settings::TokenLimit::Named(_)) => return None,
+    };
+    let counts = &block.token_counts;
+    let used = counts.input_tokens
+        + counts.output_tokens
+        + counts.cache_creation_input_tokens
+        + counts.cache_read_input_tokens;
+    (limit > 0).then_some((used, limit))
+}
+
+// "Block tokens: 4.1M / 5.0M (82%)"; menu items can't be coloured, so the
+// line is flagged once the first warning level is reached
+fn token_limit_line(used: u64, limit: u64) -> String {
+    let percent = used as f64 / limit as f64 * 100.0;
+    let line = format!(
+        "Block tokens: {} / {} ({:.0}%)",
+        format_tokens_compact(used),
+        format_tokens_compact(limit),
+        percent
+    );
+    if percent >= TOKEN_LIMIT_WARNINGS[0] {
+        format!("⚠ {}", line)
+    } else {
+        line
+    }
+}
+
+fn check_block_token_limit(block: &BlockData, limit: Option<settings::TokenLimit>) {
+    let Some((used, limit)) = block_token_usage(block, limit).filter(|_| block.is_active) else {
+        return;
+    };
+    let percent = used as f64 / limit as f64 * 100.0;
+    let mut sent = TOKEN_LIMIT_ALERTS_SENT.lock().unwrap();
+    if sent.as_ref().map(|(id, _)| id.as_str()) != Some(block.id.as_str()) {
+        *sent = Some((block.id.clone(), Vec::new()));
+    }
+    let Some((_, alerted)) = sent.as_mut() else {
+        return;
+    };
+    let crossed = newly_crossed_thresholds(&TOKEN_LIMIT_WARNINGS, percent, alerted);
+    if let Some(&highest) = crossed.last() {
+        show_alert(
+            "CCUsage token limit",
+            &format!(
+                "The active block has used {:.0}% of its token limit ({} of {})",
+                highest,
+                format_tokens_compact(used),
+                format_tokens_compact(limit)
+            ),
+        );
+        alerted.extend(crossed);
+    }
+}
+
+async fn refresh_session_data(app_handle: &tauri::AppHandle) {
+    if SHUTTING_DOWN.load(Ordering::Relaxed) {
+        return;
+    }
+    // Set refresh flag
+    IS_REFRESHING.store(true, Ordering::Relaxed);
+    {
+        let mut cache = SESSION_CACHE.lock().unwrap();
+        cache.fetch_phase = FetchPhase::InProgress {
+            has_result: cache.fetch_phase.has_result(),
+        };
+    }
+    
+    // Show the in-flight state: "Loading…" before the first result, and
+    // "Refreshing…" in the menu and title over an earlier one
+    let title = {
+        let cache = SESSION_CACHE.lock().unwrap();
+        tray_title_with_countdown(&cache, &settings::current(), chrono::Local::now())
+    };
+    if let Some(tray) = app_handle.tray_by_id("main") {
+        let _ = tray.set_title(title);
+    }
+    rebuild_menu(app_handle).await;
+
+    let display_currency = settings::current().currency;
+    currency::refresh(display_currency.as_deref()).await;
+
+    // Fetch active session data
+    let fetch_started = Instant::now();
+    let (fetch_result, block_view) = if settings::current().show_active_block {
+        tokio::join!(fetch_session_data(), fetch_active_block())
+    } else {
+        (fetch_session_data().await, None)
+    };
+    let fetch_settings = settings::current();
+    let projects = if fetch_settings.projects_shown > 0 && fetch_settings.tool_preference != ToolPreference::Codex {
+        let today = fetch_settings.day_timezone.date_of(&chrono::Local::now());
+        fetch_projects(today, fetch_settings.projects_shown).await
+    } else {
+        Vec::new()
+    };
+    let FetchResult {
+        active_block,
+        daily,
+        totals,
+        ccusage_available,
+        source,
+        error,
+        per_source,
+    } = fetch_result;
+    let outcome = match (&active_block, ccusage_available) {
+        (_, false) if error == Some(FetchError::EmptyOutput) => FetchOutcome::EmptyOutput,
+        (_, false) => FetchOutcome::Failed,
+        (Some(_), true) => FetchOutcome::Success,
+        (None, true) => FetchOutcome::NoActiveBlock,
+    };
+    record_fetch(outcome, fetch_started.elapsed());
+    
+    // A successful fetch yields a title ("$0.00" on a quiet day) unless it is
+    // under min_display_cost; an unknown state leaves the menubar icon-only
+    let cost_state = CostState::from_fetch(active_block.as_ref(), ccusage_available);
+    if let Some(ref view) = block_view {
+        check_block_token_limit(view, settings::current().block_token_limit);
+    }
+    if settings::current().show_active_block {
+        announce_block_events(block_view.as_ref());
+    }
+    if let Some(ref block) = active_block {
+        check_model_budgets(block, &settings::current().model_budgets);
+        check_cost_thresholds(block, &settings::current().daily_cost_thresholds);
+    }
+
+    // Keep days ccusage no longer reports, and bridge outages with what's stored
+    let stored_block = active_block.as_ref().filter(|_| ccusage_available);
+    let daily = history::sync(if ccusage_available { daily } else { Vec::new() }, stored_block);
+    let totals = totals.or_else(|| (!daily.is_empty()).then(|| resolve_totals(None, &daily)));
+    
+    // Update cache
+    {
+        let mut cache = SESSION_CACHE.lock().unwrap();
+        let tokens = |block: Option<&BlockData>| block.map(|block| displayed_tokens(&block.token_counts, true));
+        cache.usage_changed = ccusage_available
+            && cache.last_updated.is_some()
+            && tokens(active_block.as_ref()) != tokens(cache.active_block.as_ref());
+        if cache.usage_changed {
+            cache.last_usage_change = Some(Instant::now());
+        }
+        if ccusage_available {
+            cache.last_success = Some(Instant::now());
+        }
+        cache.active_block = active_block;
+        cache.last_updated = Some(Instant::now());
+        cache.fetch_phase = FetchPhase::Completed;
+        cache.ccusage_available = ccusage_available;
+        cache.cost_state = cost_state;
+        cache.daily_history = cap_daily_history(daily, settings::current().max_cached_days);
+        cache.block_view = block_view;
+        cache.data_source = source;
+        cache.totals = totals;
+        cache.last_error = error.clone();
+        cache.installing_ccusage = false;
+        cache.per_source = per_source;
+        cache.projects = projects;
+    }
+    if error.as_ref().is_some_and(FetchError::is_format_change) {
+        offer_compatible_pin(app_handle);
+    }
+    
+    // Update tray title, with the detail on hover
+    if let Some(tray) = app_handle.tray_by_id("main") {
+        let title = {
+            let cache = SESSION_CACHE.lock().unwrap();
+            tray_title_with_countdown(&cache, &settings::current(), chrono::Local::now())
+        };
+        let _ = tray.set_title(title);
+        let tooltip = {
+            let cache = SESSION_CACHE.lock().unwrap();
+            tray_tooltip(
+                cache.active_block.as_ref(),
+                cache.block_view.as_ref(),
+                settings::current().include_cache_tokens,
+                chrono::Local::now(),
+            )
+        };
+        let _ = tray.set_tooltip(tooltip);
+    }
+    
+    // Live-update an open dashboard
+    if app_handle.get_webview_window(DASHBOARD_WINDOW).is_some() {
+        let _ = app_handle.emit_to(DASHBOARD_WINDOW, DASHBOARD_EVENT, cached_dashboard_data());
+    }
+
+    // Rebuild and update the menu to reflect new data
+    rebuild_menu(app_handle).await;
+    
+    // Clear refresh flag
+    IS_REFRESHING.store(false, Ordering::Relaxed);
+}
+
+/// One row of the tray menu, independent of Tauri so the menu's structure can
+/// be computed and checked without a running app
+#[derive(Debug, Clone, PartialEq)]
+enum MenuEntry {
+    Item {
+        id: String,
+        label: String,
+        enabled: bool,
+        accelerator: Option<String>,
+    },
+    Check {
+        id: String,
+        label: String,
+        checked: bool,
+    },
+    Submenu {
+        id: String,
+        label: String,
+        entries: Vec<MenuEntry>,
+    },
+    /// Item drawn with a generated image next to its label
+    Icon {
+        id: String,
+        label: String,
+        icon: MenuIcon,
+    },
+    Separator,
+}
+
+/// RGBA pixels for a menu item image
+#[derive(Debug, Clone, PartialEq)]
+struct MenuIcon {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+impl MenuEntry {
+    fn item(id: impl Into<String>, label: impl Into<String>) -> Self {
+        MenuEntry::Item {
+            id: id.into(),
+            label: label.into(),
+            enabled: true,
+            accelerator: None,
+        }
+    }
+
+    fn disabled(id: impl Into<String>, label: impl Into<String>) -> Self {
+        MenuEntry::Item {
+            id: id.into(),
+            label: label.into(),
+            enabled: false,
+            accelerator: None,
+        }
+    }
+
+    fn check(id: impl Into<String>, label: impl Into<String>, checked: bool) -> Self {
+        MenuEntry::Check {
+            id: id.into(),
+            label: label.into(),
+            checked,
+        }
+    }
+
+    fn with_accelerator(mut self, shortcut: String) -> Self {
+        if let MenuEntry::Item { accelerator, .. } = &mut self {
+            *accelerator = Some(shortcut);
+        }
+        self
+    }
+
+    // Id of an item, check item or submenu; separators have none
+    #[cfg(test)]
+    fn id(&self) -> Option<&str> {
+        match self {
+            MenuEntry::Item { id, .. }
+            | MenuEntry::Check { id, .. }
+            | MenuEntry::Submenu { id, .. }
+            | MenuEntry::Icon { id, .. } => Some(id),
+            MenuEntry::Separator => None,
+        }
+    }
+}
+
+// Entries for one menu section; an empty list means the section is skipped
+fn section_entries(
+    section: Section,
+    block: &BlockData,
+    cache: &SessionData,
+    settings: &Settings,
+    today: chrono::NaiveDate,
+) -> Vec<MenuEntry> {
+    let history = &cache.daily_history;
+    // Only consulted for the "unavailable in this view" notes
+    let verbose_schema = cache
+        .data_source
+        .map(|source| source.schema)
+        .filter(|_| settings.verbose_menu);
+    let mut entries = Vec::new();
+    match section {
+        Section::Today => {
+            let title = if block.is_active {
+                "Today"
+            } else {
+                "Today (no active session)"
+            };
+            entries.push(MenuEntry::disabled("session_title", title));
+
+            // Cost and token counts
+            let cost_str = format!(
+                "Cost: {}",
+                dual_cost(block.cost_usd, settings.conversion(), currency::active().as_ref())
+            );
+            let tokens_str = format_token_line(&block.token_counts, settings.include_cache_tokens);
+            entries.push(MenuEntry::item("session_cost", cost_str));
+            if let (Some(cost), Some(budget)) = (block.cost_usd, budget::valid_budget(settings.daily_budget_usd)) {
+                let line = budget::progress_line(cost, budget);
+                let line = if budget::is_exceeded(cost, budget) {
+                    format!("{} {}", budget::OVER_BUDGET_MARKER, line)
+                } else {
+                    line
+                };
+                entries.push(MenuEntry::disabled("daily_budget", line));
+            }
+            if let Some(comparison) = block
+                .cost_usd
+                .and_then(|cost| yesterday_comparison(history, today, cost))
+            {
+                entries.push(MenuEntry::disabled("yesterday_comparison", comparison));
+            }
+            if let (Some(cost), Some(average)) = (block.cost_usd, rolling_average(history, today)) {
+                entries.push(MenuEntry::disabled(
+                    "rolling_average",
+                    rolling_average_line(average, cost, settings.heavy_day_factor),
+                ));
+            }
+            entries.push(MenuEntry::item("session_tokens", tokens_str));
+            if let Some(schema) = verbose_schema.filter(|schema| {
+                settings.include_cache_tokens && !schema.splits_cache_tokens()
+            }) {
+                entries.push(MenuEntry::disabled(
+                    "cache_split_note",
+                    format!("Cache creation split unavailable in {} view", schema.label()),
+                ));
+            }
+        }
+        Section::Times => {
+            // Session times (only if available)
+            let start_time = chrono::DateTime::parse_from_rfc3339(&block.start_time)
+                .ok()
+                .map(|dt| dt.with_timezone(&chrono::Local).format("%I:%M %p").to_string());
+            let end_time = chrono::DateTime::parse_from_rfc3339(&block.end_time)
+                .ok()
+                .map(|dt| dt.with_timezone(&chrono::Local).format("%I:%M %p").to_string());
+
+            if let Some(start) = start_time {
+                entries.push(MenuEntry::item("session_start", format!("Started: {}", start)));
+            }
+            if let Some(end) = end_time {
+                entries.push(MenuEntry::item("session_end", format!("Expires: {}", end)));
+            }
+            if let Some(schema) = verbose_schema.filter(|schema| !schema.has_session_times()) {
+                entries.push(MenuEntry::disabled(
+                    "session_times_note",
+                    format!("Session times unavailable in {} view", schema.label()),
+                ));
+            }
+        }
+        Section::Models => {
+            if !block.models.is_empty() {
+                entries.push(MenuEntry::disabled("models_header", "Models used"));
+                if settings.group_model_families {
+                    let mut families: std::collections::BTreeMap<String, Option<ModelStats>> =
+                        std::collections::BTreeMap::new();
+                    for model in &block.models {
+                        let family = families.entry(model_family(model)).or_default();
+                        if let Some(stats) = block.model_stats.get(model) {
+                            add_model_stats(family.get_or_insert_with(ModelStats::default), stats);
+                        }
+                    }
+                    for (family, stats) in families {
+                        let label = model_row_label(&family, stats.as_ref());
+                        entries.push(MenuEntry::item(format!("family_{}", family), label));
+                    }
+                } else {
+                    for model in &block.models {
+                        let label = model_row_label(&format_model_name(model), block.model_stats.get(model));
+                        entries.push(MenuEntry::item(format!("model_{}", model), label));
+                    }
+                }
+                if let (Some(billed), Some(estimate)) =
+                    (block.cost_usd, estimated_cost(block, &settings.model_prices))
+                {
+                    entries.push(MenuEntry::disabled(
+                        "billed_vs_estimate",
+                        billed_vs_estimate(billed, estimate),
+                    ));
+                }
+                entries.push(MenuEntry::item("copy_models", "Copy Model Breakdown"));
+            }
+
+            // Weekly per-model totals from the cached daily history
+            if !history.is_empty() {
+                let weekly = match weekly_model_totals(history, today) {
+                    Some(totals) => {
+                        // Grouped rows are keyed by family label rather than model id
+                        let rows: Vec<(String, String, ModelTotals)> = if settings.group_model_families {
+                            group_model_families(totals)
+                                .into_iter()
+                                .map(|(family, family_totals)| (format!("family_{}", family), family, family_totals))
+                                .collect()
+                        } else {
+                            totals
+                                .into_iter()
+                                .map(|(model, model_totals)| (model.clone(), format_model_name(&model), model_totals))
+                                .collect()
+                        };
+                        rows.into_iter()
+                            .map(|(key, name, model_totals)| {
+                                let cost = model_totals
+                                    .cost_usd
+                                    .map(money)
+                                    .unwrap_or_else(|| "n/a".to_string());
+                                let label = format!(
+                                    "{}: {} · In {} / Out {}",
+                                    name,
+                                    cost,
+                                    format_tokens_k(model_totals.input_tokens),
+                                    format_tokens_k(model_totals.output_tokens)
+                                );
+                                MenuEntry::item(format!("week_model_{}", key), label)
+                            })
+                            .collect()
+                    }
+                    None => vec![MenuEntry::disabled("week_models_none", "No model data")],
+                };
+                entries.push(MenuEntry::Submenu {
+                    id: "models_week".to_string(),
+                    label: "Models this week".to_string(),
+                    entries: weekly,
+                });
+            }
+        }
+        Section::Totals => {
+            if let Some(totals) = &cache.totals {
+                entries.push(MenuEntry::disabled(
+                    "all_time_cost",
+                    format!("All time: {}", money(totals.cost_usd)),
+                ));
+            }
+            if !history.is_empty() {
+                entries.push(MenuEntry::disabled(
+                    "month_to_date",
+                    format!("This month: {}", money(month_to_date_cost(history, today))),
+                ));
+                let streak = history::streak(history, today, budget::valid_budget(settings.daily_budget_usd));
+                if streak.days > 0 {
+                    let mut line = format!("Streak: {} day{}", streak.days, if streak.days == 1 { "" } else { "s" });
+                    if let Some(under_budget) = streak.under_budget {
+                        line.push_str(&format!(" ({} under budget)", under_budget));
+                    }
+                    entries.push(MenuEntry::disabled("usage_streak", line));
+                }
+                let days = weekday_averages(history, today)
+                    .into_iter()
+                    .map(|(weekday, average)| {
+                        let average = average
+                            .map(money)
+                            .unwrap_or_else(|| "—".to_string());
+                        MenuEntry::disabled(
+                            format!("weekday_{}", weekday),
+                            format!("{} {}", weekday, average),
+                        )
+                    })
+                    .collect();
+                entries.push(MenuEntry::Submenu {
+                    id: "by_weekday".to_string(),
+                    label: "By weekday".to_string(),
+                    entries: days,
+                });
+
+                // Calendar periods: weeks start on Monday
+                use chrono::Datelike;
+                let week_start = today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+                let month_start = today.with_day(1).unwrap_or(today);
+                entries.push(period_submenu("this_week", "This Week", &period_totals(history, week_start, today)));
+                entries.push(period_submenu("this_month", "This Month", &period_totals(history, month_start, today)));
+            }
+            if let Some(comparison) = month_vs_average(history, today) {
+                entries.push(MenuEntry::disabled("month_vs_average", comparison));
+            }
+            if let (Some(credit), false) = (settings.monthly_credit_usd, history.is_empty()) {
+                let remaining = credit - month_to_date_cost(history, today);
+                // Menu items can't be coloured, so flag overruns with a marker instead
+                let label = if remaining < 0.0 {
+                    format!("⚠ Credit exceeded by {}", money(-remaining))
+                } else {
+                    format!("Remaining credit: {}", money(remaining))
+                };
+                entries.push(MenuEntry::disabled("remaining_credit", label));
+            }
+        }
+        Section::Sparkline => {
+            if !history.is_empty() {
+                let costs = recent_daily_costs(history, today, SPARKLINE_DAYS);
+                entries.push(MenuEntry::disabled(
+                    "sparkline",
+                    format!("Last {} days: {}", SPARKLINE_DAYS, sparkline(&costs)),
+                ));
+                let week = recent_daily_costs(history, today, WEEK_CHART_DAYS);
+                entries.push(MenuEntry::Icon {
+                    id: "week_chart".to_string(),
+                    label: format!("Last {} days", WEEK_CHART_DAYS),
+                    icon: bar_chart(&week, WEEK_CHART_WIDTH, WEEK_CHART_HEIGHT),
+                });
+            }
+        }
+    }
+    entries
+}
+
+// Debug info goes to the clipboard, ready to paste into an issue. With
+// `debug_info_in_window` it also opens as a text file, which scrolls where a
+// dialog would cut long output off.
+fn show_debug_info(app: &tauri::AppHandle, debug_info: &str) {
+    match app.clipboard().write_text(debug_info.to_string()) {
+        Ok(()) => show_notification("CCUsage", "Debug info copied"),
+        Err(e) => eprintln!("Failed to copy debug info: {}", e),
+    }
+    if !settings::current().debug_info_in_window {
+        return;
+    }
+    let path = std::env::temp_dir().join("ccusage-debug-info.txt");
+    let opened = std::fs::write(&path, debug_info)
+        .map_err(|e| e.to_string())
+        .and_then(|()| tauri_plugin_opener::open_path(&path, None::<String>).map_err(|e| e.to_string()));
+    if let Err(e) = opened {
+        eprintln!("Failed to open debug info: {}", e);
+        app.dialog()
+            .message(debug_info.to_string())
+            .title("CCUsage Debug Info")
+            .show(|_| {});
+    }
+}
+
+// Save the stored daily history as CSV or JSON, by the extension picked in the
+// save dialog. Falls back to the in-memory days when the store is unavailable.
+fn export_history(app: &tauri::AppHandle) {
+    let mut days = history::sync(Vec::new(), None);
+    if days.is_empty() {
+        days = SESSION_CACHE.lock().unwrap().daily_history.clone();
+    }
+    if days.is_empty() {
+        show_notification("CCUsage", "No usage history to export yet");
+        return;
+    }
+    app.dialog()
+        .file()
+        .set_title("Export Usage")
+        .set_file_name("ccusage-history.csv")
+        .add_filter("CSV", &["csv"])
+        .add_filter("JSON", &["json"])
+        .save_file(move |path| {
+            let Some(path) = path.and_then(|path| path.into_path().ok()) else {
+                return;
+            };
+            let written = export::render(&days, export::ExportFormat::for_path(&path))
+                .and_then(|contents| std::fs::write(&path, contents).map_err(|e| e.to_string()));
+            match written {
+                Ok(()) => show_notification("CCUsage", &format!("Exported {} days", days.len())),
+                Err(e) => show_notification("CCUsage", &format!("Export failed: {}", e)),
+            }
+        });
+}
+
+// Menu rebuilds closer together than this are coalesced into one, built from
+// the latest cache once the cooldown ends. The tray title isn't limited.
+const MENU_REBUILD_COOLDOWN: Duration = Duration::from_secs(2);
+static LAST_MENU_REBUILD: Mutex<Option<Instant>> = Mutex::new(None);
+static MENU_REBUILD_PENDING: AtomicBool = AtomicBool::new(false);
+
+// How long to hold off before rebuilding, if the last rebuild was too recent
+fn menu_rebuild_delay(last: Option<Instant>, now: Instant, cooldown: Duration) -> Option<Duration> {
+    let elapsed = now.duration_since(last?);
+    (elapsed < cooldown).then(|| cooldown - elapsed)
+}
+
+async fn rebuild_menu(app: &tauri::AppHandle) {
+    let last = *LAST_MENU_REBUILD.lock().unwrap();
+    if let Some(delay) = menu_rebuild_delay(last, Instant::now(), MENU_REBUILD_COOLDOWN) {
+        // Someone is already waiting out the cooldown and will pick up this state
+        if MENU_REBUILD_PENDING.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        tokio::time::sleep(delay).await;
+        MENU_REBUILD_PENDING.store(false, Ordering::Relaxed);
+    }
+    *LAST_MENU_REBUILD.lock().unwrap() = Some(Instant::now());
+
+    if let Ok(new_menu) = build_menu(app).await {
+        if let Some(tray) = app.try_state::<Arc<tauri::tray::TrayIcon>>() {
+            let _ = tray.set_menu(Some(new_menu));
+        }
+    }
+}
+
+// Offered at most once per run so a declined prompt doesn't come back every refresh
+static DOWNGRADE_OFFERED: AtomicBool = AtomicBool::new(false);
+
+// @latest printed something no schema accepts; offer to pin the last version
+// known to work. Only meaningful while running an unpinned @ccusage/codex.
+fn offer_compatible_pin(app: &tauri::AppHandle) {
+    let current = settings::current();
+    if current.ccusage_version() != "latest"
+        || current.tool_preference == ToolPreference::Claude
+        || DOWNGRADE_OFFERED.swap(true, Ordering::Relaxed)
+    {
+        return;
+    }
+    let app_handle = app.clone();
+    app.dialog()
+        .message(format!(
+            "A newer ccusage changed its format — pin to {}?",
+            LAST_KNOWN_COMPATIBLE_VERSION
+        ))
+        .title("CCUsage")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Pin Version".to_string(),
+            "Not Now".to_string(),
+        ))
+        .show(move |confirmed| {
+            if confirmed {
+                settings::update(|settings| {
+                    settings.ccusage_version = Some(LAST_KNOWN_COMPATIBLE_VERSION.to_string());
+                });
+                forget_winning_command();
+                spawn_refresh(&app_handle);
+            }
+        });
+}
+
+// Re-render from the cache without refetching, e.g. after a display setting changed
+fn spawn_menu_rebuild(app: &tauri::AppHandle) {
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        rebuild_menu(&app_handle).await;
+    });
+}
+
+// Single-quote a value for a shell command line
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+// The npx package the cached data came from: the preferred tool, or for
+// auto-detect the one that answered (Codex unless only Claude did)
+fn detail_package(settings: &Settings, detected: &[CliTool]) -> String {
+    let claude = match settings.tool_preference {
+        ToolPreference::Claude => true,
+        ToolPreference::Codex => false,
+        ToolPreference::Auto | ToolPreference::Both => detected == [CliTool::Claude],
+    };
+    if claude {
+        "ccusage@latest".to_string()
+    } else {
+        format!("@ccusage/codex@{}", settings.ccusage_version())
+    }
+}
+
+// ccusage invocation scoped to the given block: its day for a daily entry, or
+// blocks since the day it started for a live one. Runs with the same pinned
+// version, registry and proxies as the fetch did.
+fn block_detail_command(block: &BlockData, settings: &Settings, detected: &[CliTool]) -> Option<String> {
+    let scope = if let Some(date) = block.id.strip_prefix("daily-") {
+        let day = parse_daily_date(date)?.format("%Y%m%d");
+        format!("daily --since {} --until {}", day, day)
+    } else if block.is_active && !block.id.is_empty() {
+        let start = chrono::DateTime::parse_from_rfc3339(&block.start_time).ok()?;
+        format!("blocks --since {}", settings.day_timezone.date_of(&start).format("%Y%m%d"))
+    } else {
+        return None;
+    };
+    let env: String = fetch_env(settings)
+        .into_iter()
+        .map(|(name, value)| format!("{}={} ", name, shell_quote(&value)))
+        .collect();
+    Some(format!("{}npx {} {}", env, detail_package(settings, detected), scope))
+}
+
+// Drill into the cached block's detail in Terminal; falls back to the ccusage
+// docs when the block can't be scoped or Terminal can't be scripted
+fn open_block_detail() {
+    let block = SESSION_CACHE.lock().unwrap().active_block.clone();
+    let detected = DETECTED_TOOLS.lock().unwrap().clone();
+    let settings = settings::current();
+    if let Some(command) = block
+        .as_ref()
+        .and_then(|block| block_detail_command(block, &settings, &detected))
+    {
+        #[cfg(target_os = "macos")]
+        {
+            use std::process::Command as StdCommand;
+            let script = format!(
+                r#"tell application "Terminal" to do script "{}"
+tell application "Terminal" to activate"#,
+                applescript_escape(&command)
+            );
+            if StdCommand::new("osascript").args(&["-e", &script]).spawn().is_ok() {
+                return;
+            }
+        }
+        eprintln!("Couldn't open Terminal for: {}", command);
+    }
+    let _ = tauri_plugin_opener::open_url("https://github.com/ryoppippi/ccusage", None::<String>);
+}
+
+// How often connectivity is probed, and how long one probe may take
+const REACHABILITY_INTERVAL: Duration = Duration::from_secs(15);
+const REACHABILITY_TIMEOUT: Duration = Duration::from_secs(3);
+
+// host:port that npx needs to reach: the configured registry, else npm's
+fn reachability_target(settings: &Settings) -> String {
+    let registry = settings
+        .npm_registry
+        .as_deref()
+        .map(str::trim)
+        .filter(|registry| !registry.is_empty())
+        .unwrap_or("https://registry.npmjs.org");
+    let (default_port, rest) = match registry.split_once("://") {
+        Some(("http", rest)) => (80, rest),
+        Some((_, rest)) => (443, rest),
+        None => (443, registry),
+    };
+    let host = rest.split('/').next().unwrap_or(rest);
+    if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:{}", host, default_port)
+    }
+}
+
+// Whether to probe the registry now: only while the last refresh failed, a
+// refresh would go through npx rather than local logs or the user's own
+// command, and auto-refresh isn't paused outside working hours
+fn needs_reachability_probe(settings: &Settings, cache: &SessionData, now: chrono::NaiveDateTime) -> bool {
+    settings.refresh_on_reconnect
+        && !settings.read_session_logs
+        && settings.ccusage_command().is_none()
+        && settings.auto_refresh_resumes_at(now).is_none()
+        && cache.last_error.is_some()
+}
+
+// Watch for connectivity coming back (Wi-Fi rejoin, wake from sleep) and
+// refresh right away instead of waiting for the next tick. A plain TCP connect
+// to the registry is enough to tell and needs no extra dependency.
+async fn watch_reachability(app: tauri::AppHandle) {
+    let mut was_online: Option<bool> = None;
+    loop {
+        tokio::time::sleep(REACHABILITY_INTERVAL).await;
+        let current = settings::current();
+        let probe = needs_reachability_probe(
+            &current,
+            &SESSION_CACHE.lock().unwrap(),
+            chrono::Local::now().naive_local(),
+        );
+        if !probe {
+            was_online = None;
+            continue;
+        }
+        let connect = tokio::net::TcpStream::connect(reachability_target(&current));
+        let online = matches!(
+            tokio::time::timeout(REACHABILITY_TIMEOUT, connect).await,
+            Ok(Ok(_))
+        );
+        if was_online == Some(false) && online && !IS_REFRESHING.load(Ordering::Relaxed) {
+            eprintln!("Network reachable again; refreshing");
+            refresh_session_data(&app).await;
+        }
+        was_online = Some(online);
+    }
+}
+
+// Writes arriving within this window of the first one share a refresh
+const LOG_REFRESH_DEBOUNCE: Duration = Duration::from_secs(5);
+
+// A session log gained lines
+fn is_usage_write(event: &notify::Event) -> bool {
+    matches!(event.kind, notify::EventKind::Create(_) | notify::EventKind::Modify(_))
+        && event.paths.iter().any(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+}
+
+// Refresh shortly after Claude Code or Codex logs usage instead of waiting for
+// the next tick. Runs on its own thread since notify delivers on a std channel.
+fn watch_usage_logs(app: tauri::AppHandle) {
+    use notify::Watcher;
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(sender) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("Failed to start the log watcher: {}", e);
+            return;
+        }
+    };
+    let mut watching = false;
+    for root in [CliTool::Codex, CliTool::Claude].into_iter().flat_map(native::log_roots) {
+        if root.is_dir() && watcher.watch(&root, notify::RecursiveMode::Recursive).is_ok() {
+            watching = true;
+        }
+    }
+    if !watching {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        // The watcher stops when dropped
+        let _watcher = watcher;
+        while let Ok(event) = receiver.recv() {
+            if !matches!(event, Ok(ref event) if is_usage_write(event)) {
+                continue;
+            }
+            std::thread::sleep(LOG_REFRESH_DEBOUNCE);
+            while receiver.try_recv().is_ok() {}
+            if SHUTTING_DOWN.load(Ordering::Relaxed) {
+                break;
+            }
+            if settings::current().refresh_on_log_change && !IS_REFRESHING.load(Ordering::Relaxed) {
+                spawn_refresh(&app);
+            }
+        }
+    });
+}
+
+// Time left in the active 5-hour block: "2h 13m", or "13m" in the last hour
+fn block_time_left(block: &BlockData, now: chrono::DateTime<chrono::Local>) -> Option<String> {
+    if !block.is_active {
+        return None;
+    }
+    let end = chrono::DateTime::parse_from_rfc3339(&block.end_time).ok()?;
+    let minutes = (end.with_timezone(&chrono::Local) - now).num_minutes().max(0);
+    Some(if minutes >= 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    })
+}
+
+// How far `now` is into the block's window, 0.0 to 1.0; None when the block
+// has no usable start and end times
+fn block_elapsed_fraction(block: &BlockData, now: chrono::DateTime<chrono::Local>) -> Option<f64> {
+    let start = chrono::DateTime::parse_from_rfc3339(&block.start_time).ok()?;
+    let end = chrono::DateTime::parse_from_rfc3339(&block.end_time).ok()?;
+    let length = (end - start).num_seconds();
+    if length <= 0 {
+        return None;
+    }
+    let elapsed = now.timestamp() - start.timestamp();
+    Some((elapsed as f64 / length as f64).clamp(0.0, 1.0))
+}
+
+// "▓▓▓▓░░░░░░" for 40%, rounded to the nearest cell
+fn progress_bar(fraction: f64) -> String {
+    const CELLS: usize = 10;
+    let filled = ((fraction * CELLS as f64).round() as usize).min(CELLS);
+    format!("{}{}", "▓".repeat(filled), "░".repeat(CELLS - filled))
+}
+
+// Appended to the tray title while the figures are older than the user allows
+const STALE_MARKER: &str = "⟳";
+// Appended to the tray title while a refresh over earlier figures runs
+const REFRESHING_MARKER: &str = "…";
+
+// How long ago the last successful refresh was, once that is past the
+// configured limit; None while the figures are current or none were shown yet
+fn stale_for(cache: &SessionData, settings: &Settings) -> Option<Duration> {
+    let elapsed = cache.last_success?.elapsed();
+    settings.stale_after().filter(|limit| elapsed >= *limit).map(|_| elapsed)
+}
+
+// Today's cost, followed by the block countdown when the user wants it there
+// and the stale marker when the figures are old
+fn tray_title_with_countdown(
+    cache: &SessionData,
+    settings: &Settings,
+    now: chrono::DateTime<chrono::Local>,
+) -> Option<String> {
+    let title = format_title(cache.active_block.as_ref(), cache.cost_state, settings)?;
+    let countdown = cache
+        .block_view
+        .as_ref()
+        .filter(|_| settings.show_active_block && settings.block_countdown_in_title)
+        .and_then(|block| block_time_left(block, now));
+    let title = match countdown {
+        Some(left) => format!("{} · {}", title, left),
+        None => title,
+    };
+    if cache.fetch_phase == (FetchPhase::InProgress { has_result: true }) {
+        return Some(format!("{} {}", title, REFRESHING_MARKER));
+    }
+    Some(match stale_for(cache, settings) {
+        Some(_) => format!("{} {}", title, STALE_MARKER),
+        None => title,
+    })
+}
+
+/// The block the start/reset notifications are following
+#[derive(Debug, Clone, PartialEq)]
+struct WatchedBlock {
+    id: String,
+    end: Option<chrono::DateTime<chrono::FixedOffset>>,
+    reset_announced: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum BlockEvent {
+    /// The watched block's window ran out
+    Reset,
+    /// A different block became active; carries its end time, e.g. "17:00"
+    Started { ends_at: Option<String> },
+}
+
+static WATCHED_BLOCK: Mutex<Option<WatchedBlock>> = Mutex::new(None);
+
+// Compare the active block with the one being watched. The first block seen
+// after launch is adopted quietly; only changes after that are announced.
+fn block_events(
+    watched: &mut Option<WatchedBlock>,
+    current: Option<&BlockData>,
+    now: chrono::DateTime<chrono::Local>,
+) -> Vec<BlockEvent> {
+    let mut events = Vec::new();
+    if let Some(block) = watched.as_mut() {
+        if !block.reset_announced && block.end.is_some_and(|end| end.timestamp() <= now.timestamp()) {
+            block.reset_announced = true;
+            events.push(BlockEvent::Reset);
+        }
+    }
+    let Some(current) = current.filter(|block| block.is_active) else {
+        return events;
+    };
+    if watched.as_ref().is_some_and(|block| block.id == current.id) {
+        return events;
+    }
+    let end = chrono::DateTime::parse_from_rfc3339(&current.end_time).ok();
+    if watched.is_some() {
+        events.push(BlockEvent::Started {
+            ends_at: end.map(|end| end.with_timezone(&chrono::Local).format("%H:%M").to_string()),
+        });
+    }
+    *watched = Some(WatchedBlock {
+        id: current.id.clone(),
+        end,
+        reset_announced: false,
+    });
+    events
+}
+
+fn announce_block_events(current: Option<&BlockData>) {
+    if !settings::current().notify_block_changes {
+        return;
+    }
+    let events = block_events(&mut WATCHED_BLOCK.lock().unwrap(), current, chrono::Local::now());
+    for event in events {
+        match event {
+            BlockEvent::Reset => show_notification("CCUsage", "Rate-limit window reset"),
+            BlockEvent::Started { ends_at: Some(ends_at) } => {
+                show_notification("CCUsage", &format!("New usage block started; it ends at {}", ends_at))
+            }
+            BlockEvent::Started { ends_at: None } => show_notification("CCUsage", "New usage block started"),
+        }
+    }
+}
+
+// How often the block countdown is redrawn between refreshes
+const COUNTDOWN_INTERVAL: Duration = Duration::from_secs(60);
+
+// Keep "Block ends in …" and the stale marker current without refetching:
+// redraw the title and menu once a minute while a block is active or the
+// figures have gone stale
+async fn tick_block_countdown(app: tauri::AppHandle) {
+    loop {
+        tokio::time::sleep(COUNTDOWN_INTERVAL).await;
+        let current = settings::current();
+        let title = {
+            let cache = SESSION_CACHE.lock().unwrap();
+            // Windows run out between refreshes, so check for resets here
+            announce_block_events(cache.block_view.as_ref());
+            let active = current.show_active_block
+                && cache.block_view.as_ref().is_some_and(|block| block.is_active);
+            let stale = stale_for(&cache, &current).is_some();
+            if !(active || stale) || IS_REFRESHING.load(Ordering::Relaxed) {
+                continue;
+            }
+            tray_title_with_countdown(&cache, &current, chrono::Local::now())
+        };
+        if let Some(tray) = app.tray_by_id("main") {
+            let _ = tray.set_title(title);
+        }
+        rebuild_menu(&app).await;
+    }
+}
+
+// Register or remove the login item. The setting is the source of truth, so a
+// login item removed in System Settings comes back only if the user re-enables it.
+fn apply_launch_at_login(app: &tauri::AppHandle, enabled: bool) {
+    use tauri_plugin_autostart::ManagerExt;
+    let autolaunch = app.autolaunch();
+    if autolaunch.is_enabled().ok() == Some(enabled) {
+        return;
+    }
+    let result = if enabled { autolaunch.enable() } else { autolaunch.disable() };
+    if let Err(e) = result {
+        eprintln!("Failed to update the login item: {}", e);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn activation_policy(show_dock_icon: bool) -> tauri::ActivationPolicy {
+    if show_dock_icon {
+        tauri::ActivationPolicy::Regular
+    } else {
+        tauri::ActivationPolicy::Accessory
+    }
+}
+
+fn spawn_refresh(app: &tauri::AppHandle) {
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        // Force refresh all data; this also rebuilds the menu
+        refresh_session_data(&app_handle).await;
+    });
+}
+
+// The whole tray menu for the given cache state, top to bottom
+fn menu_entries(
+    cache: &SessionData,
+    settings: &Settings,
+    detected: &[CliTool],
+    now: chrono::DateTime<chrono::Local>,
+) -> Vec<MenuEntry> {
+    let today = settings.day_timezone.date_of(&now);
+    // CCUsage header (simple, no timestamp)
+    let mut entries = vec![MenuEntry::item("ccusage_header", "CCUsage")];
+    if let Some(updated) = cache.last_updated {
+        entries.push(MenuEntry::disabled("last_updated", updated_ago(updated.elapsed())));
+    }
+    if cache.fetch_phase == (FetchPhase::InProgress { has_result: true }) {
+        entries.push(MenuEntry::disabled("refreshing", "Refreshing…"));
+    } else if let Some(stale) = stale_for(cache, settings) {
+        entries.push(MenuEntry::disabled(
+            "stale_warning",
+            format!("⚠ Figures are {}m old; refreshes aren't getting through", stale.as_secs() / 60),
+        ));
+    }
+    entries.push(MenuEntry::Separator);
+
+    let has_attempted_fetch = cache.fetch_phase.has_result();
+    let ccusage_available = cache.ccusage_available;
+
+    // Recovery action, only while the last fetch failed
+    if has_attempted_fetch && !ccusage_available {
+        entries.push(MenuEntry::item("retry", "Retry now"));
+        entries.push(MenuEntry::Separator);
+    }
+
+    // Block and day side by side, whichever of the two views answered
+    if has_attempted_fetch && settings.show_active_block {
+        let cost_or_na = |block: Option<&BlockData>| {
+            block
+                .and_then(|block| block.cost_usd)
+                .map(money)
+                .unwrap_or_else(|| "n/a".to_string())
+        };
+        entries.push(MenuEntry::disabled(
+            "combined_costs",
+            format!(
+                "Active block: {} · Today: {}",
+                cost_or_na(cache.block_view.as_ref()),
+                cost_or_na(cache.active_block.as_ref())
+            ),
+        ));
+        if let Some(view) = cache.block_view.as_ref() {
+            if let Some(left) = block_time_left(view, now) {
+                let label = match block_elapsed_fraction(view, now) {
+                    Some(fraction) => format!("{} {} left", progress_bar(fraction), left),
+                    None => format!("Block ends in {}", left),
+                };
+                entries.push(MenuEntry::disabled("block_countdown", label));
+            }
+            if let Some((used, limit)) = block_token_usage(view, settings.block_token_limit) {
+                entries.push(MenuEntry::disabled("block_token_limit", token_limit_line(used, limit)));
+            }
+        }
+        entries.push(MenuEntry::Separator);
+    }
+
+    // Multi-source mode: each tool's share of the combined figures
+    if has_attempted_fetch && !cache.per_source.is_empty() {
+        for (tool, block) in &cache.per_source {
+            let cost = block
+                .cost_usd
+                .map(money)
+                .unwrap_or_else(|| "n/a".to_string());
+            entries.push(MenuEntry::disabled(
+                format!("source_total_{}", tool.short_label().to_lowercase().replace(' ', "_")),
+                format!(
+                    "{}: {} · In {} / Out {}",
+                    tool.short_label(),
+                    cost,
+                    format_tokens_k(block.token_counts.input_tokens),
+                    format_tokens_k(block.token_counts.output_tokens)
+                ),
+            ));
+        }
+        entries.push(MenuEntry::Separator);
+    }
+
+    if let Some(block) = &cache.active_block {
+        // Render sections in the user's order, separated from each other
+        for section in &settings.section_order {
+            let items = section_entries(*section, block, cache, settings, today);
+            if items.is_empty() {
+                continue;
+            }
+            entries.extend(items);
+            entries.push(MenuEntry::Separator);
+        }
+        if !cache.projects.is_empty() {
+            entries.push(MenuEntry::Submenu {
+                id: "projects".to_string(),
+                label: "Projects".to_string(),
+                entries: cache
+                    .projects
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (name, cost))| {
+                        MenuEntry::disabled(format!("project_{}", index), format!("{}: {}", name, money(*cost)))
+                    })
+                    .collect(),
+            });
+            entries.push(MenuEntry::Separator);
+        }
+    } else if has_attempted_fetch {
+        // We've tried to fetch
+        entries.push(MenuEntry::disabled("session_title", "Today"));
+        entries.push(MenuEntry::item("no_session", "No usage today"));
+
+        // Only show error if ccusage is actually not available. A command that ran
+        // but gave nothing usable says so instead of suggesting an install.
+        if !ccusage_available {
+            match &cache.last_error {
+                Some(FetchError::CommandNotFound) => {
+                    entries.push(MenuEntry::disabled("fetch_error", FetchError::CommandNotFound.message()));
+                    entries.push(MenuEntry::item("install_msg", "Install: npm i -g @ccusage/codex"));
+                }
+                Some(error) => entries.push(MenuEntry::disabled("fetch_error", error.message())),
+                None => {
+                    entries.push(MenuEntry::disabled("error_msg", "@ccusage/codex may not be installed"));
+                    entries.push(MenuEntry::item("install_msg", "Install: npm i -g @ccusage/codex"));
+                }
+            }
+        }
+        entries.push(MenuEntry::Separator);
+    } else {
+        // Still loading; the first fetch can take a while when npx has to
+        // download ccusage, so say so rather than look frozen
+        let loading = match cache.fetch_phase {
+            FetchPhase::Pending => "Loading...",
+            _ if cache.installing_ccusage => "Installing ccusage on first use…",
+            _ => "Loading… (first run may take a while)",
+        };
+        entries.push(MenuEntry::disabled("session_title", "Today"));
+        entries.push(MenuEntry::disabled("loading", loading));
+        entries.push(MenuEntry::Separator);
+    }
+
+    entries.push(MenuEntry::check(
+        "include_cache_tokens",
+        "Include Cache Tokens",
+        settings.include_cache_tokens,
+    ));
+    entries.push(MenuEntry::check("show_dock_icon", "Show in Dock", settings.show_dock_icon));
+    entries.push(MenuEntry::check("launch_at_login", "Launch at Login", settings.launch_at_login));
+
+    // Data source: which ccusage CLI to read, plus whatever was last detected
+    let detected_label = if detected.is_empty() {
+        "Detected: none yet".to_string()
+    } else {
+        let names: Vec<&str> = detected.iter().map(|tool| tool.label()).collect();
+        format!("Detected: {}", names.join(", "))
+    };
+    let source_choice = |id: &str, label: &str, preference: ToolPreference| {
+        MenuEntry::check(id, label, settings.tool_preference == preference)
+    };
+    entries.push(MenuEntry::Submenu {
+        id: "data_source".to_string(),
+        label: "Data Source".to_string(),
+        entries: vec![
+            source_choice("source_auto", "Auto (whichever has usage today)", ToolPreference::Auto),
+            source_choice("source_codex", CliTool::Codex.label(), ToolPreference::Codex),
+            source_choice("source_claude", CliTool::Claude.label(), ToolPreference::Claude),
+            source_choice("source_both", "Both (combined)", ToolPreference::Both),
+            MenuEntry::Separator,
+            MenuEntry::disabled("source_detected", detected_label),
+        ],
+    });
+
+    let accelerator = |configured: &str, default: &'static str| -> String {
+        if settings::is_valid_accelerator(configured) {
+            configured.to_string()
+        } else {
+            eprintln!("Invalid accelerator {:?}; using {}", configured, default);
+            default.to_string()
+        }
+    };
+
+    // Working hours are wall-clock times whatever the day boundary
+    if let Some(resumes_at) = settings.auto_refresh_resumes_at(now.naive_local()) {
+        entries.push(MenuEntry::disabled(
+            "auto_refresh_paused",
+            "Outside working hours — auto-refresh paused",
+        ));
+        entries.push(MenuEntry::disabled(
+            "auto_refresh_resumes",
+            format!("Resumes {}", resumes_at.format("%a %H:%M")),
+        ));
+    }
+
+    entries.push(MenuEntry::item("preferences", "Preferences…").with_accelerator("Cmd+,".to_string()));
+    if settings.show_dashboard_item {
+        entries.push(MenuEntry::item("dashboard", "Open Dashboard…"));
+    }
+    entries.push(MenuEntry::item("export_history", "Export…"));
+    if updates::enabled() {
+        entries.push(MenuEntry::item("check_updates", "Check for Updates…"));
+    }
+
+    // Refresh button
+    entries.push(MenuEntry::item("refresh", "Refresh").with_accelerator(accelerator(
+        &settings.refresh_accelerator,
+        settings::DEFAULT_REFRESH_ACCELERATOR,
+    )));
+
+    // Diagnostics, hidden when the user opts out of advanced items
+    if settings.show_advanced_items {
+        // Debug info (useful for troubleshooting)
+        entries.push(MenuEntry::item("debug", "Debug Info").with_accelerator(accelerator(
+            &settings.debug_accelerator,
+            settings::DEFAULT_DEBUG_ACCELERATOR,
+        )));
+        entries.push(MenuEntry::item("rescan_environment", "Rescan Environment"));
+        entries.push(MenuEntry::item("copy_raw_json", "Copy Raw JSON"));
+        entries.push(MenuEntry::Submenu {
+            id: "settings_transfer".to_string(),
+            label: "Settings".to_string(),
+            entries: vec![
+                MenuEntry::item("export_settings", "Copy Settings as JSON"),
+                MenuEntry::item("import_settings", "Import Settings from Clipboard"),
+            ],
+        });
+    }
+    entries.push(MenuEntry::Separator);
+
+    // Quit
+    entries.push(MenuEntry::item("quit", "Quit").with_accelerator("Cmd+Q".to_string()));
+    entries
+}
+
+// "Updated just now" / "Updated 3m ago" / "Updated 2h ago"; only as fresh as
+// the last menu rebuild
+fn updated_ago(elapsed: Duration) -> String {
+    let minutes = elapsed.as_secs() / 60;
+    match minutes {
+        0 => "Updated just now".to_string(),
+        1..=59 => format!("Updated {}m ago", minutes),
+        _ => format!("Updated {}h ago", minutes / 60),
+    }
+}
+
+// Adaptive cadence: quick while a session is producing usage, the configured
+// interval otherwise, and a slow poll once nothing has moved for an hour
+const ACTIVE_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+const IDLE_REFRESH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const IDLE_AFTER: Duration = Duration::from_secs(60 * 60);
+
+fn adaptive_refresh_interval(base: Duration, usage_changed: bool, since_change: Option<Duration>) -> Duration {
+    if usage_changed {
+        ACTIVE_REFRESH_INTERVAL.min(base)
+    } else if since_change.map_or(true, |since| since >= IDLE_AFTER) {
+        IDLE_REFRESH_INTERVAL.max(base)
+    } else {
+        base
+    }
+}
+
+// The interval the periodic refresh runs at right now
+fn refresh_period(settings: &Settings, cache: &SessionData) -> Duration {
+    if !settings.adaptive_refresh {
+        return settings.refresh_interval();
+    }
+    adaptive_refresh_interval(
+        settings.refresh_interval(),
+        cache.usage_changed,
+        cache.last_usage_change.map(|changed| changed.elapsed()),
+    )
+}
+
+// Turn a non-separator entry into its Tauri menu item
+fn build_entry(
+    app: &tauri::AppHandle,
+    entry: &MenuEntry,
+) -> tauri::Result<Box<dyn IsMenuItem<tauri::Wry>>> {
+    Ok(match entry {
+        MenuEntry::Item { id, label, enabled, accelerator } => {
+            let mut builder = MenuItemBuilder::with_id(id.as_str(), label).enabled(*enabled);
+            if let Some(accelerator) = accelerator {
+                builder = builder.accelerator(accelerator);
+            }
+            Box::new(builder.build(app)?)
+        }
+        MenuEntry::Check { id, label, checked } => Box::new(
+            CheckMenuItemBuilder::with_id(id.as_str(), label)
+                .checked(*checked)
+                .build(app)?,
+        ),
+        MenuEntry::Submenu { id, label, entries } => {
+            let mut builder = SubmenuBuilder::with_id(app, id.as_str(), label);
+            for entry in entries {
+                builder = match entry {
+                    MenuEntry::Separator => builder.separator(),
+                    _ => builder.item(build_entry(app, entry)?.as_ref()),
+                };
+            }
+            Box::new(builder.build()?)
+        }
+        MenuEntry::Icon { id, label, icon } => Box::new(
+            IconMenuItemBuilder::with_id(id.as_str(), label)
+                .icon(tauri::image::Image::new_owned(icon.rgba.clone(), icon.width, icon.height))
+                .build(app)?,
+        ),
+        MenuEntry::Separator => unreachable!("separators are added by the enclosing builder"),
+    })
+}
+
+async fn build_menu(app: &tauri::AppHandle) -> Result<tauri::menu::Menu<tauri::Wry>, Box<dyn std::error::Error>> {
+    let cache = SESSION_CACHE.lock().unwrap().clone();
+    let detected = DETECTED_TOOLS.lock().unwrap().clone();
+    let entries = menu_entries(
+        &cache,
+        &settings::current(),
+        &detected,
+        chrono::Local::now(),
+    );
+
+    let mut menu_builder = MenuBuilder::new(app);
+    for entry in &entries {
+        menu_builder = match entry {
+            MenuEntry::Separator => menu_builder.separator(),
+            _ => menu_builder.item(build_entry(app, entry)?.as_ref()),
+        };
+    }
+    Ok(menu_builder.build()?)
+}
+
+
+// `--once`: fetch a single time, print the result as JSON and exit without a
+// tray. Handy for cron jobs, CI smoke tests and checking a user's setup.
+fn run_once() -> i32 {
+    let result = tauri::async_runtime::block_on(fetch_session_data());
+    let summary = serde_json::json!({
+        "ccusage_available": result.ccusage_available,
+        "today": result.active_block,
+        "error": result.error.as_ref().map(FetchError::message),
+    });
+    match serde_json::to_string_pretty(&summary) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Failed to serialize summary: {}", e),
+    }
+    if result.ccusage_available {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    if std::env::args().any(|arg| arg == "--once") {
+        std::process::exit(run_once());
+    }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
     tauri::Builder::default()
+        // Must be registered first: a second launch hands off to this instance
+        // and exits before creating its own tray or refresh loop
+        .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+            show_notification("CCUsage", "Already running in the menubar");
+            spawn_refresh(app);
+        }))
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![])
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_autostart::init(
+            tauri_plugin_autostart::MacosLauncher::LaunchAgent,
+            None,
+        ))
+        .invoke_handler(tauri::generate_handler![
+            get_recent_fetches,
+            get_data_source,
+            preview_title,
+            get_daily_series,
+            get_daily_history,
+            get_dashboard_data,
+            export_settings,
+            import_settings,
+            get_status,
+            get_settings,
+            set_settings,
+            get_usage_snapshot
+        ])
         .setup(|app| {
             #[cfg(target_os = "macos")]
-            app.set_activation_policy(tauri::ActivationPolicy::Accessory);
+            app.set_activation_policy(activation_policy(settings::current().show_dock_icon));
 
             let app_handle = app.handle().clone();
+            apply_launch_at_login(&app_handle, settings::current().launch_at_login);
             
             // Start periodic refresh task
             let periodic_handle = app_handle.clone();
             tauri::async_runtime::spawn(async move {
-                let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(120)); // 2 minutes
-                loop {
-                    interval.tick().await;
+                let mut period = settings::current().refresh_interval();
+                let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                // Don't burst after time spent in aligned mode
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                while !SHUTTING_DOWN.load(Ordering::Relaxed) {
+                    // Pick up a changed interval from the config or activity
+                    let settings = settings::current();
+                    let wanted = refresh_period(&settings, &SESSION_CACHE.lock().unwrap());
+                    if wanted != period {
+                        period = wanted;
+                        interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                    }
+                    let now = chrono::Local::now().naive_local();
+                    if let Some(resumes_at) = settings.auto_refresh_resumes_at(now) {
+                        // Sleep straight through to the next window instead of
+                        // ticking and skipping; show the paused state meanwhile
+                        rebuild_menu(&periodic_handle).await;
+                        let wait = (resumes_at - now).to_std().unwrap_or(period);
+                        tokio::time::sleep(wait).await;
+                        interval.reset();
+                    } else if settings.align_refresh_to_clock {
+                        let delay = delay_to_next_boundary(chrono::Local::now(), period);
+                        tokio::time::sleep(delay).await;
+                    } else {
+                        interval.tick().await;
+                    }
                     // Only refresh if not already refreshing and we have initial data
                     if !IS_REFRESHING.load(Ordering::Relaxed) {
                         let should_refresh = {
@@ -520,17 +4173,38 @@ pub fn run() {
                 }
             });
 
+            tauri::async_runtime::spawn(watch_reachability(app_handle.clone()));
+            watch_usage_logs(app_handle.clone());
+            tauri::async_runtime::spawn(tick_block_countdown(app_handle.clone()));
+            updates::enable(&app_handle);
+
+            // Spot a first fetch that will have to download ccusage, so the
+            // loading line can say so
+            tauri::async_runtime::spawn({
+                let app_handle = app_handle.clone();
+                async move {
+                    if first_use_install_pending().await {
+                        {
+                            let mut cache = SESSION_CACHE.lock().unwrap();
+                            if cache.fetch_phase.has_result() {
+                                return;
+                            }
+                            cache.installing_ccusage = true;
+                        }
+                        rebuild_menu(&app_handle).await;
+                    }
+                }
+            });
+
             tauri::async_runtime::spawn(async move {
-                // Initial data refresh on app startup
-                refresh_session_data(&app_handle).await;
-                
+                // Create the tray first so the first (possibly slow) fetch shows
+                // its loading state instead of an empty menubar
                 match build_menu(&app_handle).await {
                     Ok(menu) => {
                         // Get initial title from cache
                         let initial_title = {
                             let cache = SESSION_CACHE.lock().unwrap();
-                            cache.active_block.as_ref()
-                                .map(|block| format!("${:.2}", block.cost_usd))
+                            format_title(cache.active_block.as_ref(), cache.cost_state, &settings::current())
                         };
                         
                         let tray = TrayIconBuilder::with_id("main")
@@ -542,7 +4216,24 @@ pub fn run() {
                             .icon_as_template(true)
                             .title(initial_title.unwrap_or_default())
                             .menu(&menu)
-                            .show_menu_on_left_click(true)
+                            .show_menu_on_left_click(settings::current().left_click_action.opens_menu())
+                            .on_tray_icon_event(move |tray, event| {
+                                if let TrayIconEvent::Click {
+                                    button: MouseButton::Left,
+                                    button_state: MouseButtonState::Up,
+                                    ..
+                                } = event
+                                {
+                                    // Read per click so a changed setting applies at once
+                                    match settings::current().left_click_action {
+                                        LeftClickAction::Refresh if !IS_REFRESHING.load(Ordering::Relaxed) => {
+                                            spawn_refresh(tray.app_handle());
+                                        }
+                                        LeftClickAction::OpenDashboard => open_dashboard(tray.app_handle()),
+                                        _ => {}
+                                    }
+                                }
+                            })
                             .on_menu_event({
                                 let _app_handle = app_handle.clone();
                                 move |app, event| match event.id().as_ref() {
@@ -559,41 +4250,121 @@ pub fn run() {
                                         );
                                     }
                                     "quit" => {
+                                        SHUTTING_DOWN.store(true, Ordering::Relaxed);
                                         app.exit(0);
                                     }
                                     "refresh" => {
-                                        let app_handle = app.app_handle().clone();
+                                        spawn_refresh(app);
+                                    }
+                                    // Both drop the remembered command so the fetch
+                                    // rescans the whole environment
+                                    "retry" | "rescan_environment" => {
+                                        forget_winning_command();
+                                        spawn_refresh(app);
+                                    }
+                                    "source_auto" | "source_codex" | "source_claude" | "source_both" => {
+                                        let preference = match event.id().as_ref() {
+                                            "source_auto" => ToolPreference::Auto,
+                                            "source_claude" => ToolPreference::Claude,
+                                            "source_both" => ToolPreference::Both,
+                                            _ => ToolPreference::Codex,
+                                        };
+                                        settings::update(|settings| settings.tool_preference = preference);
+                                        // Remembered commands may belong to the other tool
+                                        forget_winning_command();
+                                        spawn_refresh(app);
+                                    }
+                                    "include_cache_tokens" => {
+                                        settings::update(|settings| {
+                                            settings.include_cache_tokens = !settings.include_cache_tokens;
+                                        });
+                                        spawn_menu_rebuild(app);
+                                    }
+                                    "show_dock_icon" => {
+                                        let updated = settings::update(|settings| {
+                                            settings.show_dock_icon = !settings.show_dock_icon;
+                                        });
+                                        #[cfg(target_os = "macos")]
+                                        if let Err(e) = app.set_activation_policy(activation_policy(updated.show_dock_icon)) {
+                                            eprintln!("Failed to change Dock visibility: {}", e);
+                                        }
+                                        #[cfg(not(target_os = "macos"))]
+                                        let _ = updated;
+                                        spawn_menu_rebuild(app);
+                                    }
+                                    "launch_at_login" => {
+                                        let updated = settings::update(|settings| {
+                                            settings.launch_at_login = !settings.launch_at_login;
+                                        });
+                                        apply_launch_at_login(app, updated.launch_at_login);
+                                        spawn_menu_rebuild(app);
+                                    }
+                                    "session_cost" => {
+                                        if settings::current().open_detail_on_cost_click {
+                                            open_block_detail();
+                                        }
+                                    }
+                                    "copy_raw_json" => {
+                                        let app_handle = app.clone();
                                         tauri::async_runtime::spawn(async move {
-                                            // Force refresh all data
-                                            refresh_session_data(&app_handle).await;
-                                            
-                                            // Rebuild menu with fresh data
-                                            if let Ok(new_menu) = build_menu(&app_handle).await {
-                                                if let Some(tray) = app_handle.try_state::<Arc<tauri::tray::TrayIcon>>() {
-                                                    let _ = tray.set_menu(Some(new_menu));
-                                                }
+                                            // Fetch once if nothing has been captured yet
+                                            if LAST_RAW_OUTPUT.lock().unwrap().is_none() {
+                                                refresh_session_data(&app_handle).await;
+                                            }
+                                            let raw = LAST_RAW_OUTPUT.lock().unwrap().clone();
+                                            match raw {
+                                                Some(raw) => match app_handle.clipboard().write_text(raw) {
+                                                    Ok(()) => show_notification("CCUsage", "Raw ccusage JSON copied"),
+                                                    Err(e) => eprintln!("Failed to copy raw JSON: {}", e),
+                                                },
+                                                None => show_notification("CCUsage", "No ccusage output captured yet"),
                                             }
                                         });
                                     }
+                                    "export_settings" => {
+                                        let (json, redacted) = settings::export();
+                                        match app.clipboard().write_text(json) {
+                                            Ok(()) if redacted => show_notification(
+                                                "CCUsage",
+                                                "Settings copied; proxy credentials were left out",
+                                            ),
+                                            Ok(()) => show_notification("CCUsage", "Settings copied"),
+                                            Err(e) => eprintln!("Failed to copy settings: {}", e),
+                                        }
+                                    }
+                                    "import_settings" => {
+                                        let json = app.clipboard().read_text().unwrap_or_default();
+                                        match import_settings(app.clone(), json) {
+                                            Ok(true) => show_notification(
+                                                "CCUsage",
+                                                "Settings imported; this Mac's command, registry and proxies were kept",
+                                            ),
+                                            Ok(false) => show_notification("CCUsage", "Settings imported"),
+                                            Err(e) => show_notification("CCUsage", &e),
+                                        }
+                                    }
+                                    "copy_models" => {
+                                        let block = SESSION_CACHE.lock().unwrap().active_block.clone();
+                                        if let Some(block) = block {
+                                            let table = model_breakdown_table(&block);
+                                            match app.clipboard().write_text(table) {
+                                                Ok(()) => show_notification("CCUsage", "Model breakdown copied"),
+                                                Err(e) => eprintln!("Failed to copy model breakdown: {}", e),
+                                            }
+                                        }
+                                    }
+                                    "preferences" => open_preferences(app),
+                                    "dashboard" => open_dashboard(app),
+                                    "export_history" => export_history(app),
+                                    "check_updates" => {
+                                        tauri::async_runtime::spawn(updates::check(app.clone(), true));
+                                    }
                                     "debug" => {
+                                        let app_handle = app.clone();
                                         tauri::async_runtime::spawn(async move {
                                             let debug_info = get_debug_info().await;
                                             println!("=== DEBUG INFO ===\n{}\n==================", debug_info);
-                                            
-                                            // Also try to show in a dialog if possible
-                                            #[cfg(target_os = "macos")]
-                                            {
-                                                use std::process::Command as StdCommand;
-                                                let _ = StdCommand::new("osascript")
-                                                    .args(&[
-                                                        "-e",
-                                                        &format!(
-                                                            r#"display dialog "{}" buttons {{"OK"}} default button "OK" with title "CCUsage Debug Info""#,
-                                                            debug_info.replace("\"", "\\\"").replace("\n", "\\n")
-                                                        ),
-                                                    ])
-                                                    .spawn();
-                                            }
+                                            show_debug_info(&app_handle, &debug_info);
                                         });
                                     }
                                     _ => {}
@@ -609,10 +4380,1357 @@ pub fn run() {
                         eprintln!("Failed to build initial menu: {}", e);
                     }
                 }
+
+                // Initial data refresh on app startup
+                refresh_session_data(&app_handle).await;
             });
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Covers every exit path, not only the Quit item
+                SHUTTING_DOWN.store(true, Ordering::Relaxed);
+                let flushed = settings::config_path()
+                    .ok_or_else(|| "No config directory available".to_string())
+                    .and_then(|path| settings::flush(&path));
+                if let Err(e) = flushed {
+                    eprintln!("Failed to flush settings on exit: {}", e);
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(date: &str, cost_usd: f64) -> DailyEntry {
+        DailyEntry {
+            date: date.to_string(),
+            input_tokens: 1000,
+            cached_input_tokens: 0,
+            output_tokens: 500,
+            total_tokens: 1500,
+            cost_usd: Some(cost_usd),
+            models: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn delay_to_next_boundary_lands_on_wall_clock_multiples() {
+        use chrono::TimeZone;
+        let at = |h, m, sec| chrono::Local.with_ymd_and_hms(2025, 9, 18, h, m, sec).unwrap();
+        let two_minutes = Duration::from_secs(120);
+        assert_eq!(delay_to_next_boundary(at(10, 3, 30), two_minutes), Duration::from_secs(30));
+        assert_eq!(delay_to_next_boundary(at(10, 4, 0), two_minutes), Duration::from_secs(120));
+        assert_eq!(
+            delay_to_next_boundary(at(10, 59, 0), Duration::from_secs(300)),
+            Duration::from_secs(60)
+        );
+    }
+
+    fn parse_daily(tokens: &str) -> DailyEntry {
+        let json = format!(
+            r#"{{"date": "Sep 18, 2025", {} "costUSD": 1.5, "models": {{}}}}"#,
+            tokens
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn token_counts_accept_integers() {
+        let entry = parse_daily(r#""inputTokens": 1200, "cachedInputTokens": 0, "outputTokens": 300, "totalTokens": 1500,"#);
+        assert_eq!(entry.input_tokens, 1200);
+        assert_eq!(entry.output_tokens, 300);
+        assert_eq!(entry.total_tokens, 1500);
+    }
+
+    #[test]
+    fn token_counts_accept_numeric_strings() {
+        let entry = parse_daily(r#""inputTokens": "1200", "outputTokens": " 300 ", "totalTokens": "1500.9","#);
+        assert_eq!(entry.input_tokens, 1200);
+        assert_eq!(entry.output_tokens, 300);
+        assert_eq!(entry.total_tokens, 1500);
+    }
+
+    #[test]
+    fn token_counts_truncate_floats() {
+        let entry = parse_daily(r#""inputTokens": 1200.7, "outputTokens": 2.5e2, "totalTokens": -3.0,"#);
+        assert_eq!(entry.input_tokens, 1200);
+        assert_eq!(entry.output_tokens, 250);
+        assert_eq!(entry.total_tokens, 0);
+    }
+
+    #[test]
+    fn token_counts_default_null_missing_and_garbage_to_zero() {
+        let entry = parse_daily(r#""inputTokens": null, "outputTokens": "lots","#);
+        assert_eq!(entry.input_tokens, 0);
+        assert_eq!(entry.cached_input_tokens, 0);
+        assert_eq!(entry.output_tokens, 0);
+        assert_eq!(entry.total_tokens, 0);
+    }
+
+    #[test]
+    fn block_token_counts_accept_mixed_representations() {
+        let counts: TokenCounts = serde_json::from_str(
+            r#"{"inputTokens": "10", "outputTokens": 20.9, "cacheReadInputTokens": null}"#,
+        )
+        .unwrap();
+        assert_eq!(counts.input_tokens, 10);
+        assert_eq!(counts.output_tokens, 20);
+        assert_eq!(counts.cache_creation_input_tokens, 0);
+        assert_eq!(counts.cache_read_input_tokens, 0);
+    }
+
+    #[test]
+    fn cap_daily_history_keeps_most_recent_days() {
+        let daily = vec![
+            entry("Sep 18, 2025", 3.0),
+            entry("Sep 16, 2025", 1.0),
+            entry("not a date", 9.0),
+            entry("Sep 17, 2025", 2.0),
+        ];
+        let capped = cap_daily_history(daily, 2);
+        let dates: Vec<&str> = capped.iter().map(|entry| entry.date.as_str()).collect();
+        assert_eq!(dates, vec!["Sep 17, 2025", "Sep 18, 2025"]);
+
+        assert_eq!(cap_daily_history(vec![entry("Sep 18, 2025", 3.0)], 2).len(), 1);
+        assert!(cap_daily_history(vec![entry("Sep 18, 2025", 3.0)], 0).is_empty());
+    }
+
+    #[test]
+    fn format_cost_fixed_always_shows_cents() {
+        assert_eq!(format_cost(0.42, CostPrecision::Fixed, 100.0), "$0.42");
+        assert_eq!(format_cost(142.37, CostPrecision::Fixed, 100.0), "$142.37");
+    }
+
+    #[test]
+    fn format_cost_adaptive_switches_at_threshold() {
+        let adaptive = |cost| format_cost(cost, CostPrecision::Adaptive, 100.0);
+        assert_eq!(adaptive(0.0), "$0.00");
+        assert_eq!(adaptive(0.42), "$0.42");
+        assert_eq!(adaptive(99.99), "$99.99");
+        assert_eq!(adaptive(99.994), "$99.99");
+        assert_eq!(adaptive(99.996), "$100");
+        assert_eq!(adaptive(100.0), "$100");
+        assert_eq!(adaptive(142.37), "$142");
+        assert_eq!(format_cost(12.4, CostPrecision::Adaptive, 10.0), "$12");
+    }
+
+    #[test]
+    fn totals_object_is_preferred_over_summing() {
+        let response: DailyResponse = serde_json::from_str(
+            r#"{
+                "daily": [
+                    {"date": "Sep 17, 2025", "inputTokens": 100, "outputTokens": 10, "totalTokens": 110, "costUSD": 1.0, "models": {}},
+                    {"date": "Sep 18, 2025", "inputTokens": 200, "outputTokens": 20, "totalTokens": 220, "costUSD": 2.0, "models": {}}
+                ],
+                "totals": {"inputTokens": 900, "outputTokens": 90, "totalTokens": 990, "costUSD": 9.5}
+            }"#,
+        )
+        .unwrap();
+        let totals = resolve_totals(response.totals.as_ref(), &response.daily);
+        assert_eq!(totals.cost_usd, 9.5);
+        assert_eq!(totals.input_tokens, 900);
+        assert_eq!(totals.total_tokens, 990);
+    }
+
+    #[test]
+    fn totals_accept_claude_cost_field_name() {
+        let totals: Totals = serde_json::from_str(r#"{"totalCost": 4.25, "totalTokens": 10}"#).unwrap();
+        assert_eq!(totals.cost_usd, 4.25);
+        assert_eq!(totals.total_tokens, 10);
+    }
+
+    #[test]
+    fn totals_fall_back_to_summing_entries() {
+        let response: DailyResponse = serde_json::from_str(
+            r#"{"daily": [
+                {"date": "Sep 17, 2025", "inputTokens": 100, "outputTokens": 10, "totalTokens": 110, "costUSD": 1.0, "models": {}},
+                {"date": "Sep 18, 2025", "inputTokens": 200, "outputTokens": 20, "totalTokens": 220, "costUSD": 2.5, "models": {}}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(response.totals.is_none());
+        let totals = resolve_totals(None, &response.daily);
+        assert_eq!(totals.cost_usd, 3.5);
+        assert_eq!(totals.input_tokens, 300);
+        assert_eq!(totals.output_tokens, 30);
+        assert_eq!(totals.total_tokens, 330);
+    }
+
+    #[test]
+    fn today_entry_finds_real_entry_in_either_format() {
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 9, 18).unwrap();
+        for date in ["Sep 18, 2025", "2025-09-18"] {
+            let entries = vec![entry("Sep 17, 2025", 1.0), entry(date, 2.5)];
+            assert_eq!(today_entry(&entries, today).cost_usd, Some(2.5));
+        }
+    }
+
+    #[test]
+    fn synthesized_zero_entry_matches_like_real_entries() {
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 9, 18).unwrap();
+        for (yesterday, expected) in [("Sep 17, 2025", "Sep 18, 2025"), ("2025-09-17", "2025-09-18")] {
+            let entries = vec![entry(yesterday, 1.0)];
+            let zero = today_entry(&entries, today);
+            assert_eq!(zero.date, expected);
+            assert_eq!(zero.cost_usd, Some(0.0));
+            assert!(is_entry_for(&zero, today));
+        }
+    }
+
+    #[test]
+    fn entries_with_missing_dates_are_skipped() {
+        let json = r#"{"daily": [
+            {"costUSD": 9.0, "models": {}},
+            {"date": null, "costUSD": 8.0, "models": {}},
+            {"date": "", "costUSD": 7.0, "models": {}},
+            {"date": "2025-09-18", "costUSD": 2.5, "models": {}}
+        ]}"#;
+        let response: DailyResponse = serde_json::from_str(json).unwrap();
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 9, 18).unwrap();
+        assert_eq!(today_entry(&response.daily, today).cost_usd, Some(2.5));
+
+        // Only malformed dates: today falls back to zero in the default layout
+        let zero = today_entry(&response.daily[..3], today);
+        assert_eq!(zero.cost_usd, Some(0.0));
+        assert_eq!(zero.date, "Sep 18, 2025");
+    }
+
+    #[test]
+    fn synthesized_entry_without_history_uses_default_format() {
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 9, 8).unwrap();
+        let zero = today_entry(&[], today);
+        assert_eq!(zero.date, "Sep 08, 2025");
+        assert!(is_entry_for(&zero, today));
+    }
+
+    fn block(cost_usd: f64, models: &[&str]) -> BlockData {
+        BlockData {
+            id: "daily-2025-06-02".to_string(),
+            start_time: String::new(),
+            end_time: String::new(),
+            is_active: true,
+            token_counts: TokenCounts {
+                input_tokens: 1000,
+                output_tokens: 500,
+                cache_creation_input_tokens: 0,
+                cache_read_input_tokens: 0,
+            },
+            cost_usd: Some(cost_usd),
+            models: models.iter().map(|model| model.to_string()).collect(),
+            model_stats: HashMap::new(),
+            token_limit_status: None,
+        }
+    }
+
+    fn cache(phase: FetchPhase, available: bool, active_block: Option<BlockData>) -> SessionData {
+        SessionData {
+            fetch_phase: phase,
+            ccusage_available: available,
+            active_block,
+            ..SessionData::EMPTY
+        }
+    }
+
+    // Ids of the top-level entries, with "-" standing in for separators
+    fn menu_now() -> chrono::DateTime<chrono::Local> {
+        use chrono::TimeZone;
+        chrono::Local.with_ymd_and_hms(2025, 6, 2, 12, 0, 0).unwrap()
+    }
+
+    fn menu_ids(cache: &SessionData, settings: &Settings) -> Vec<String> {
+        menu_entries(cache, settings, &[], menu_now())
+            .iter()
+            .map(|entry| entry.id().unwrap_or("-").to_string())
+            .collect()
+    }
+
+    const FOOTER: &[&str] = &[
+        "include_cache_tokens",
+        "show_dock_icon",
+        "launch_at_login",
+        "data_source",
+        "preferences",
+        "dashboard",
+        "export_history",
+        "refresh",
+        "debug",
+        "rescan_environment",
+        "copy_raw_json",
+        "settings_transfer",
+        "-",
+        "quit",
+    ];
+
+    fn with_footer(head: &[&str]) -> Vec<String> {
+        head.iter().chain(FOOTER).map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn menu_while_loading() {
+        let ids = menu_ids(&cache(FetchPhase::Pending, false, None), &Settings::default());
+        assert_eq!(
+            ids,
+            with_footer(&["ccusage_header", "-", "session_title", "loading", "-"])
+        );
+    }
+
+    #[test]
+    fn menu_without_usage_when_ccusage_is_available() {
+        let ids = menu_ids(&cache(FetchPhase::Completed, true, None), &Settings::default());
+        assert_eq!(
+            ids,
+            with_footer(&["ccusage_header", "-", "session_title", "no_session", "-"])
+        );
+    }
+
+    #[test]
+    fn menu_without_ccusage_offers_retry_and_install() {
+        let ids = menu_ids(&cache(FetchPhase::Completed, false, None), &Settings::default());
+        assert_eq!(
+            ids,
+            with_footer(&[
+                "ccusage_header",
+                "-",
+                "retry",
+                "-",
+                "session_title",
+                "no_session",
+                "error_msg",
+                "install_msg",
+                "-",
+            ])
+        );
+    }
+
+    #[test]
+    fn menu_with_active_block_renders_sections_in_order() {
+        let block = block(3.5, &["gpt-5-codex"]);
+        let ids = menu_ids(&cache(FetchPhase::Completed, true, Some(block)), &Settings::default());
+        assert_eq!(
+            ids,
+            with_footer(&[
+                "ccusage_header",
+                "-",
+                "session_title",
+                "session_cost",
+                "session_tokens",
+                "-",
+                "models_header",
+                "model_gpt-5-codex",
+                "copy_models",
+                "-",
+            ])
+        );
+    }
+
+    #[test]
+    fn zero_cost_day_without_models_skips_empty_sections() {
+        let cache = cache(FetchPhase::Completed, true, Some(block(0.0, &[])));
+        let entries = menu_entries(&cache, &Settings::default(), &[], menu_now());
+        assert!(entries.contains(&MenuEntry::item("session_cost", "Cost: $0.00")));
+        assert!(!entries.iter().any(|entry| entry.id() == Some("models_header")));
+        // Still a single separator after the Today section, none for the skipped ones
+        let ids = menu_ids(&cache, &Settings::default());
+        assert_eq!(&ids[2..6], ["session_title", "session_cost", "session_tokens", "-"]);
+        assert_eq!(ids[6], "include_cache_tokens");
+    }
+
+    #[test]
+    fn menu_hides_advanced_items_when_disabled() {
+        let settings = Settings {
+            show_advanced_items: false,
+            ..Settings::default()
+        };
+        let ids = menu_ids(&cache(FetchPhase::Pending, false, None), &settings);
+        assert!(!ids.iter().any(|id| id == "debug" || id == "copy_raw_json"));
+        assert!(ids.iter().any(|id| id == "refresh"));
+    }
+
+    #[test]
+    fn fetch_env_sets_registry_and_both_proxy_cases() {
+        let settings = Settings {
+            npm_registry: Some("https://npm.example.com/".to_string()),
+            https_proxy: Some(" http://proxy:3128 ".to_string()),
+            http_proxy: Some(String::new()),
+            ..Settings::default()
+        };
+        assert_eq!(
+            fetch_env(&settings),
+            vec![
+                ("npm_config_registry", "https://npm.example.com/".to_string()),
+                ("HTTPS_PROXY", "http://proxy:3128".to_string()),
+                ("https_proxy", "http://proxy:3128".to_string()),
+            ]
+        );
+        assert!(fetch_env(&Settings::default()).is_empty());
+    }
+
+    #[test]
+    fn block_detail_follows_the_tool_and_block_start() {
+        let daily = block(1.0, &[]);
+        let pinned = Settings {
+            ccusage_version: Some("17.1.0".to_string()),
+            npm_registry: Some("https://npm.example.com/".to_string()),
+            ..Settings::default()
+        };
+        assert_eq!(
+            block_detail_command(&daily, &pinned, &[]).as_deref(),
+            Some("npm_config_registry='https://npm.example.com/' npx @ccusage/codex@17.1.0 daily --since 20250602 --until 20250602")
+        );
+
+        let live = BlockData {
+            id: "2025-06-02T09:00:00.000Z".to_string(),
+            start_time: "2025-06-02T09:00:00Z".to_string(),
+            ..daily
+        };
+        let claude = Settings {
+            tool_preference: ToolPreference::Claude,
+            day_timezone: DayTimezone::Utc,
+            ..Settings::default()
+        };
+        assert_eq!(
+            block_detail_command(&live, &claude, &[]).as_deref(),
+            Some("TZ='UTC' npx ccusage@latest blocks --since 20250602")
+        );
+        let auto = Settings {
+            tool_preference: ToolPreference::Auto,
+            ..Settings::default()
+        };
+        assert_eq!(detail_package(&auto, &[CliTool::Claude]), "ccusage@latest");
+        assert_eq!(detail_package(&auto, &[CliTool::Codex, CliTool::Claude]), "@ccusage/codex@latest");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn menu_shows_paused_auto_refresh_outside_working_hours() {
+        // menu_now() is a Monday at noon
+        let settings = Settings {
+            working_hours: Some(settings::WorkingHours {
+                start_hour: 14,
+                ..settings::WorkingHours::default()
+            }),
+            ..Settings::default()
+        };
+        let entries = menu_entries(&SessionData::EMPTY, &settings, &[], menu_now());
+        assert!(entries.contains(&MenuEntry::disabled("auto_refresh_resumes", "Resumes Mon 14:00")));
+        let ids = menu_ids(&SessionData::EMPTY, &Settings::default());
+        assert!(!ids.iter().any(|id| id == "auto_refresh_paused"));
+    }
+
+    #[test]
+    fn empty_and_whitespace_stdout_is_empty_output() {
+        assert_eq!(non_empty_output(""), Err(FetchError::EmptyOutput));
+        assert_eq!(non_empty_output("  \n\t\r\n"), Err(FetchError::EmptyOutput));
+        assert_eq!(non_empty_output("{}"), Ok("{}"));
+    }
+
+    #[test]
+    fn menu_reports_empty_output_instead_of_install_hint() {
+        let cache = SessionData {
+            last_error: Some(FetchError::EmptyOutput),
+            ..cache(FetchPhase::Completed, false, None)
+        };
+        let entries = menu_entries(&cache, &Settings::default(), &[], menu_now());
+        assert!(entries.contains(&MenuEntry::disabled("fetch_error", "ccusage returned no output")));
+        assert!(!entries.iter().any(|entry| entry.id() == Some("install_msg")));
+    }
+
+    #[test]
+    fn tray_title_prepends_configured_prefix() {
+        let settings = Settings {
+            tray_prefix: Some("◆".to_string()),
+            ..Settings::default()
+        };
+        assert_eq!(format_title(None, CostState::Spent(3.5), &settings), Some("◆ $3.50".to_string()));
+        assert_eq!(format_title(None, CostState::Unknown, &settings), None);
+        let blank = Settings {
+            tray_prefix: Some("  ".to_string()),
+            ..Settings::default()
+        };
+        assert_eq!(format_title(None, CostState::Zero, &blank), Some("$0.00".to_string()));
+    }
+
+    #[test]
+    fn title_preview_follows_the_given_settings() {
+        let adaptive = Settings {
+            cost_precision: CostPrecision::Adaptive,
+            tray_prefix: Some("cc".to_string()),
+            ..Settings::default()
+        };
+        assert_eq!(title_preview(None, CostState::Spent(142.37), &adaptive), "cc $142");
+        let hidden = Settings {
+            min_display_cost: 200.0,
+            ..Settings::default()
+        };
+        assert_eq!(title_preview(None, CostState::Spent(142.37), &hidden), "");
+    }
+
+    #[test]
+    fn blocks_without_is_active_parse_as_inactive() {
+        let json = r#"{"blocks": [
+            {"id": "a", "startTime": "", "endTime": "", "tokenCounts": {}, "costUSD": 1.0, "models": []},
+            {"id": "b", "startTime": "", "endTime": "", "isActive": null, "tokenCounts": {}, "costUSD": 2.0, "models": []},
+            {"id": "c", "startTime": "", "endTime": "", "isActive": true, "tokenCounts": {}, "costUSD": 3.0, "models": []}
+        ]}"#;
+        let (schema, active) = parse_block_schemas(json).unwrap();
+        assert_eq!(schema, Schema::Blocks);
+        assert_eq!(active.unwrap().id, "c");
+    }
+
+    #[test]
+    fn yesterday_comparison_uses_full_day() {
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 9, 18).unwrap();
+        let history = vec![entry("Sep 17, 2025", 2.0), entry("Sep 18, 2025", 2.8)];
+        assert_eq!(
+            yesterday_comparison(&history, today, 2.8).as_deref(),
+            Some("Yesterday (full day): $2.00 (today +40%)")
+        );
+        assert_eq!(
+            yesterday_comparison(&history, today, 1.5).as_deref(),
+            Some("Yesterday (full day): $2.00 (today −25%)")
+        );
+        assert_eq!(yesterday_comparison(&history[1..], today, 2.8), None);
+        let free = vec![entry("Sep 17, 2025", 0.0)];
+        assert_eq!(yesterday_comparison(&free, today, 2.8).as_deref(), Some("Yesterday (full day): $0.00"));
+    }
+
+    #[test]
+    fn rolling_average_covers_the_week_before_today() {
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 9, 18).unwrap();
+        let history = vec![
+            entry("Sep 10, 2025", 50.0),
+            entry("Sep 11, 2025", 7.0),
+            entry("Sep 17, 2025", 7.0),
+            entry("Sep 18, 2025", 30.0),
+        ];
+        assert_eq!(rolling_average(&history, today), Some(2.0));
+        assert_eq!(rolling_average(&history[..1], today), None);
+        assert_eq!(rolling_average_line(2.0, 3.0, 1.5), "7-day avg: $2.00/day ▲ today 1.5×");
+        assert_eq!(rolling_average_line(2.0, 2.9, 1.5), "7-day avg: $2.00/day");
+        assert_eq!(rolling_average_line(2.0, 30.0, 0.0), "7-day avg: $2.00/day");
+    }
+
+    #[test]
+    fn dual_cost_shows_conversion_only_when_configured() {
+        let euro = settings::CurrencyConversion {
+            symbol: "€".to_string(),
+            usd_rate: 0.92,
+        };
+        assert_eq!(dual_cost(Some(3.21), Some(&euro), None), "$3.21 (≈ €2.95)");
+        assert_eq!(dual_cost(Some(3.21), None, None), "$3.21");
+        // The daily rate wins over the fixed one, and USD stays
+        let pounds = currency::CachedRate {
+            code: "GBP".to_string(),
+            usd_rate: 0.8,
+            fetched_on: chrono::NaiveDate::from_ymd_opt(2025, 6, 2).unwrap(),
+        };
+        assert_eq!(dual_cost(Some(3.21), Some(&euro), Some(&pounds)), "$3.21 (≈ £2.57)");
+        assert_eq!(dual_cost(Some(3.21), None, Some(&pounds)), "$3.21 (≈ £2.57)");
+        let settings = Settings {
+            conversion: Some(settings::CurrencyConversion { usd_rate: 0.0, ..euro }),
+            ..Settings::default()
+        };
+        assert!(settings.conversion().is_none());
+    }
+
+    #[test]
+    fn only_eagain_and_interrupts_are_retried() {
+        use std::io::{Error, ErrorKind};
+        assert!(is_transient_spawn_error(&Error::from(ErrorKind::WouldBlock)));
+        assert!(is_transient_spawn_error(&Error::from(ErrorKind::Interrupted)));
+        assert!(!is_transient_spawn_error(&Error::from(ErrorKind::NotFound)));
+        assert!(!is_transient_spawn_error(&Error::from(ErrorKind::PermissionDenied)));
+    }
+
+    #[test]
+    fn weekday_averages_need_enough_complete_days() {
+        // Sep 8 and 15, 2025 are Mondays; Sep 18 is today (a Thursday)
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 9, 18).unwrap();
+        let history = vec![
+            entry("2025-09-08", 4.0),
+            entry("2025-09-15", 2.0),
+            entry("2025-09-16", 3.0),
+            entry("2025-09-11", 1.0),
+            entry("2025-09-18", 9.0),
+        ];
+        let averages = weekday_averages(&history, today);
+        assert_eq!(averages.len(), 7);
+        assert_eq!(averages[0], (chrono::Weekday::Mon, Some(3.0)));
+        assert_eq!(averages[1], (chrono::Weekday::Tue, None));
+        // Today's partial day doesn't count toward Thursday
+        assert_eq!(averages[3], (chrono::Weekday::Thu, None));
+        assert_eq!(averages[6], (chrono::Weekday::Sun, None));
+    }
+
+    #[test]
+    fn missing_cost_parses_as_none_and_shows_tokens() {
+        let daily: DailyResponse = serde_json::from_str(
+            r#"{"daily": [{"date": "2025-09-18", "inputTokens": 1200, "outputTokens": 300, "models": {}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(daily.daily[0].cost_usd, None);
+        let block: BlockData = serde_json::from_str(
+            r#"{"id": "b", "startTime": "", "endTime": "", "isActive": true, "tokenCounts": {"inputTokens": 1200, "outputTokens": 300}, "costUSD": null, "models": []}"#,
+        )
+        .unwrap();
+        assert_eq!(block.cost_usd, None);
+        assert_eq!(
+            format_title(None, CostState::TokensOnly(1500), &Settings::default()),
+            Some("1.5K tok".to_string())
+        );
+        assert_eq!(dual_cost(None, None, None), "n/a");
+        // Days without a cost add nothing to sums and aren't compared
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 9, 19).unwrap();
+        assert_eq!(month_to_date_cost(&daily.daily, today), 0.0);
+        assert_eq!(yesterday_comparison(&daily.daily, today, 1.0), None);
+    }
+
+    #[test]
+    fn estimate_needs_every_model_priced() {
+        let mut block = block(3.21, &["gpt-5", "gpt-5-codex"]);
+        let stats = |input, output| ModelStats {
+            input_tokens: Some(input),
+            output_tokens: Some(output),
+            ..ModelStats::default()
+        };
+        block.model_stats.insert("gpt-5".to_string(), stats(1_000_000, 100_000));
+        block.model_stats.insert("gpt-5-codex".to_string(), stats(400_000, 20_000));
+        let price = settings::ModelPrice {
+            input: 1.25,
+            output: 10.0,
+            cached_input: None,
+        };
+        let mut prices = HashMap::from([("gpt-5".to_string(), price)]);
+        assert_eq!(estimated_cost(&block, &prices), None);
+
+        prices.insert("GPT-5 Codex".to_string(), price);
+        let estimate = estimated_cost(&block, &prices).unwrap();
+        assert!((estimate - 2.95).abs() < 1e-9);
+        assert_eq!(billed_vs_estimate(3.21, 3.40), "Billed $3.21 · Est $3.40 · −$0.19");
+        assert_eq!(billed_vs_estimate(3.21, estimate), "Billed $3.21 · Est $2.95 · +$0.26");
+    }
+
+    #[test]
+    fn sparkline_covers_a_fixed_window() {
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 9, 18).unwrap();
+        let history = vec![entry("2025-08-01", 50.0), entry("2025-09-16", 1.0), entry("2025-09-18", 4.0)];
+        let costs = recent_daily_costs(&history, today, 4);
+        assert_eq!(costs, vec![0.0, 1.0, 0.0, 4.0]);
+        assert_eq!(sparkline(&costs), "▁▃▁█");
+        assert_eq!(sparkline(&[0.0, 0.0]), "▁▁");
+    }
+
+    #[test]
+    fn custom_command_appends_the_report_unless_exact() {
+        assert_eq!(custom_daily_command("bunx ccusage", false, "daily --json"), "bunx ccusage daily --json");
+        for command in ["bunx ccusage@latest daily --json -b", "ccusage daily -b", "/opt/daily/ccusage blocks --json"] {
+            assert_eq!(custom_daily_command(command, true, "daily --json"), command);
+        }
+    }
+
+    #[test]
+    fn dashboard_charts_the_last_thirty_days() {
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 6, 30).unwrap();
+        let mut recent = entry("Jun 30, 2025", 3.0);
+        recent.cached_input_tokens = 400;
+        recent.total_tokens = 1500;
+        recent.models = HashMap::from([(
+            "claude-sonnet-4-20250514".to_string(),
+            ModelStats {
+                input_tokens: Some(1000),
+                output_tokens: Some(100),
+                cost_usd: Some(3.0),
+                ..ModelStats::default()
+            },
+        )]);
+        let history = vec![entry("May 31, 2025", 9.0), entry("Jun 01, 2025", 1.0), recent];
+        let data = dashboard_data(&history, today, false);
+
+        assert_eq!(data.days.len(), DASHBOARD_DAYS);
+        assert_eq!(data.days[0].date, chrono::NaiveDate::from_ymd_opt(2025, 6, 1).unwrap());
+        assert_eq!(data.days[0].cost_usd, 1.0);
+        assert_eq!(data.days[1].cost_usd, 0.0);
+        let last = data.days.last().unwrap();
+        assert_eq!((last.cached_tokens, last.uncached_tokens), (400, 1100));
+        assert_eq!(
+            data.models,
+            vec![DashboardModel {
+                name: format_model_name("claude-sonnet-4-20250514"),
+                input_tokens: 1000,
+                output_tokens: 100,
+                cost_usd: Some(3.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn downsample_buckets_old_days_into_weeks() {
+        let day = |d: u32, cost_usd| SeriesPoint {
+            date: chrono::NaiveDate::from_ymd_opt(2025, 9, d).unwrap(),
+            cost_usd,
+        };
+        // Sep 1 and Sep 8, 2025 are Mondays
+        let points: Vec<SeriesPoint> = (1..=18).map(|d| day(d, 1.0)).collect();
+        let series = downsample(&points, 5);
+        assert_eq!(series[0], day(1, 7.0));
+        assert_eq!(series[1], day(8, 6.0));
+        assert_eq!(&series[2..], &points[13..]);
+        assert_eq!(downsample(&points, 30), points);
+        assert!(downsample(&[], 30).is_empty());
+    }
+
+    #[test]
+    fn reachability_target_follows_the_registry() {
+        assert_eq!(reachability_target(&Settings::default()), "registry.npmjs.org:443");
+        let with_registry = |registry: &str| Settings {
+            npm_registry: Some(registry.to_string()),
+            ..Settings::default()
+        };
+        assert_eq!(
+            reachability_target(&with_registry("https://npm.corp.example/api/npm/")),
+            "npm.corp.example:443"
+        );
+        assert_eq!(
+            reachability_target(&with_registry("http://10.0.0.5:4873")),
+            "10.0.0.5:4873"
+        );
+        assert_eq!(reachability_target(&with_registry("http://mirror.local")), "mirror.local:80");
+    }
+
+    #[test]
+    fn shared_command_labels_run_the_same_line() {
+        let mut lines: HashMap<&str, (&str, Vec<String>)> = HashMap::new();
+        let lists = [
+            ccusage_commands("daily --json"),
+            tool_commands(CliTool::Codex, "daily --json"),
+            tool_commands(CliTool::Claude, "daily --json"),
+        ];
+        for command in lists.iter().flatten() {
+            let line = (command.program, command.args.clone());
+            assert_eq!(lines.entry(command.label).or_insert_with(|| line.clone()), &line, "{}", command.label);
+        }
+    }
+
+    #[test]
+    fn block_comes_from_the_preferred_or_detected_tool() {
+        assert_eq!(block_tools(ToolPreference::Claude, &[CliTool::Codex]), vec![CliTool::Claude]);
+        assert_eq!(block_tools(ToolPreference::Codex, &[]), vec![CliTool::Codex]);
+        assert_eq!(block_tools(ToolPreference::Auto, &[CliTool::Claude]), vec![CliTool::Claude]);
+        assert_eq!(block_tools(ToolPreference::Both, &[]), vec![CliTool::Codex, CliTool::Claude]);
+
+        let finished = BlockData {
+            is_active: false,
+            ..block(9.0, &[])
+        };
+        let picked = |codex: Option<BlockData>, claude: Option<BlockData>| {
+            pick_block(codex, claude).map(|block| block.cost_usd)
+        };
+        assert_eq!(picked(Some(finished.clone()), Some(block(1.0, &[]))), Some(Some(1.0)));
+        assert_eq!(picked(Some(block(2.0, &[])), Some(block(3.0, &[]))), Some(Some(3.0)));
+        assert_eq!(picked(Some(block(2.0, &[])), Some(block(2.0, &[]))), Some(Some(2.0)));
+        assert_eq!(picked(None, Some(finished)), Some(Some(9.0)));
+        assert_eq!(picked(None, None), None);
+    }
+
+    #[test]
+    fn reachability_is_probed_only_to_recover_a_failed_refresh() {
+        let now = chrono::NaiveDate::from_ymd_opt(2025, 9, 18).unwrap().and_hms_opt(22, 0, 0).unwrap();
+        let failed = SessionData {
+            last_error: Some(FetchError::Timeout),
+            ..cache(FetchPhase::Completed, false, None)
+        };
+        assert!(needs_reachability_probe(&Settings::default(), &failed, now));
+        assert!(!needs_reachability_probe(
+            &Settings::default(),
+            &cache(FetchPhase::Completed, true, None),
+            now
+        ));
+        let local = Settings {
+            read_session_logs: true,
+            ..Settings::default()
+        };
+        assert!(!needs_reachability_probe(&local, &failed, now));
+        // 22:00 on a Thursday is outside the default working hours
+        let paused = Settings {
+            working_hours: Some(settings::WorkingHours::default()),
+            ..Settings::default()
+        };
+        assert!(!needs_reachability_probe(&paused, &failed, now));
+    }
+
+    #[test]
+    fn first_fetch_explains_the_wait() {
+        let loading_label = |cache: &SessionData| {
+            menu_entries(cache, &Settings::default(), &[], menu_now())
+                .into_iter()
+                .find(|entry| entry.id() == Some("loading"))
+        };
+        let in_flight = cache(FetchPhase::InProgress { has_result: false }, false, None);
+        assert_eq!(
+            loading_label(&in_flight),
+            Some(MenuEntry::disabled("loading", "Loading… (first run may take a while)"))
+        );
+        let installing = SessionData {
+            installing_ccusage: true,
+            ..in_flight
+        };
+        assert_eq!(
+            loading_label(&installing),
+            Some(MenuEntry::disabled("loading", "Installing ccusage on first use…"))
+        );
+    }
+
+    #[test]
+    fn tray_tooltip_summarizes_today() {
+        let block = block(3.21, &["gpt-5", "gpt-5-codex"]);
+        let now = menu_now();
+        assert_eq!(
+            tray_tooltip(Some(&block), None, false, now).as_deref(),
+            Some("Today: $3.21 · 2 models\nTokens: 1.5K (In 1.0K / Out 0.5K)\nCache: 0.0K read / 0.0K written")
+        );
+        assert_eq!(tray_tooltip(None, None, false, now), None);
+
+        let end = now + chrono::Duration::minutes(133);
+        let view = BlockData { end_time: end.to_rfc3339(), ..block.clone() };
+        let tooltip = tray_tooltip(Some(&block), Some(&view), false, now).unwrap();
+        assert_eq!(
+            tooltip.lines().last(),
+            Some(format!("Block ends {} (2h 13m left)", end.format("%H:%M")).as_str())
+        );
+    }
+
+    // Stable text form of the menu: one line per entry, submenus indented
+    fn menu_snapshot(entries: &[MenuEntry], depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        for entry in entries {
+            match entry {
+                MenuEntry::Item { id, label, enabled, accelerator } => {
+                    out.push_str(&format!("{}item {} \"{}\"", indent, id, label));
+                    if !enabled {
+                        out.push_str(" [disabled]");
+                    }
+                    if let Some(accelerator) = accelerator {
+                        out.push_str(&format!(" <{}>", accelerator));
+                    }
+                }
+                MenuEntry::Check { id, label, checked } => {
+                    let mark = if *checked { "x" } else { " " };
+                    out.push_str(&format!("{}check {} \"{}\" [{}]", indent, id, label, mark));
+                }
+                MenuEntry::Submenu { id, label, entries } => {
+                    out.push_str(&format!("{}submenu {} \"{}\"\n", indent, id, label));
+                    menu_snapshot(entries, depth + 1, out);
+                    continue;
+                }
+                MenuEntry::Icon { id, label, icon } => {
+                    out.push_str(&format!("{}icon {} \"{}\" {}x{}", indent, id, label, icon.width, icon.height));
+                }
+                MenuEntry::Separator => out.push_str(&format!("{}---", indent)),
+            }
+            out.push('\n');
+        }
+    }
+
+    #[test]
+    fn menu_matches_golden_snapshot() {
+        let mut block = block(3.5, &["gpt-5", "gpt-5-codex"]);
+        block.start_time = "2025-06-02T09:00:00Z".to_string();
+        block.end_time = "2025-06-02T14:00:00Z".to_string();
+        let cache = SessionData {
+            daily_history: vec![entry("2025-06-01", 2.0), entry("2025-06-02", 3.5)],
+            totals: Some(Totals {
+                cost_usd: 42.0,
+                ..Totals::default()
+            }),
+            ..cache(FetchPhase::Completed, true, Some(block.clone()))
+        };
+        let mut rendered = String::new();
+        menu_snapshot(&menu_entries(&cache, &Settings::default(), &[], menu_now()), 0, &mut rendered);
+
+        // Block times render in the machine's timezone, so the golden file
+        // leaves placeholders for them
+        let local_time = |time: &str| {
+            chrono::DateTime::parse_from_rfc3339(time)
+                .unwrap()
+                .with_timezone(&chrono::Local)
+                .format("%I:%M %p")
+                .to_string()
+        };
+        let expected = include_str!("snapshots/menu_active_block.txt")
+            .replace("{start}", &local_time(&block.start_time))
+            .replace("{end}", &local_time(&block.end_time));
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn verbose_menu_notes_what_the_daily_view_lacks() {
+        let cache = SessionData {
+            data_source: Some(DataSource {
+                schema: Schema::Daily,
+                command: "login-zsh npx",
+            }),
+            ..cache(FetchPhase::Completed, true, Some(block(1.0, &[])))
+        };
+        let ids = |settings: &Settings| menu_ids(&cache, settings);
+        assert!(!ids(&Settings::default()).iter().any(|id| id.ends_with("_note")));
+
+        let verbose = Settings {
+            verbose_menu: true,
+            include_cache_tokens: true,
+            ..Settings::default()
+        };
+        let verbose_ids = ids(&verbose);
+        assert!(verbose_ids.iter().any(|id| id == "session_times_note"));
+        assert!(verbose_ids.iter().any(|id| id == "cache_split_note"));
+    }
+
+    #[test]
+    fn block_model_breakdowns_are_optional() {
+        let flat = r#"{"id": "a", "startTime": "", "endTime": "", "isActive": true, "tokenCounts": {}, "costUSD": 1.0, "models": ["gpt-5"]}"#;
+        let block: BlockData = serde_json::from_str(flat).unwrap();
+        assert!(block.model_stats.is_empty());
+
+        let nested = r#"{"id": "a", "startTime": "", "endTime": "", "isActive": true, "tokenCounts": {}, "costUSD": 1.0, "models": ["gpt-5"],
+            "modelBreakdowns": [{"modelName": "gpt-5", "inputTokens": 1000, "outputTokens": 200, "cacheReadTokens": 50, "cost": 0.9}]}"#;
+        let block: BlockData = serde_json::from_str(nested).unwrap();
+        let stats = &block.model_stats["gpt-5"];
+        assert_eq!(stats.input_tokens, Some(1000));
+        assert_eq!(stats.cached_input_tokens, Some(50));
+        assert_eq!(stats.total_tokens, Some(1250));
+        assert_eq!(stats.cost_usd, Some(0.9));
+    }
+
+    #[test]
+    fn menu_rebuilds_wait_out_the_cooldown() {
+        let now = Instant::now();
+        let cooldown = Duration::from_secs(2);
+        assert_eq!(menu_rebuild_delay(None, now, cooldown), None);
+        assert_eq!(
+            menu_rebuild_delay(Some(now), now + Duration::from_millis(500), cooldown),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(menu_rebuild_delay(Some(now), now + cooldown, cooldown), None);
+    }
+
+    #[test]
+    fn finished_sessions_still_count_as_today() {
+        use chrono::TimeZone;
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+        let local = |h| chrono::Local.with_ymd_and_hms(2025, 6, 2, h, 0, 0).unwrap().to_rfc3339();
+        let finished = |start: String, cost, model: &str| BlockData {
+            start_time: start,
+            is_active: false,
+            ..block(cost, &[model])
+        };
+        let blocks = vec![
+            finished(local(8), 1.5, "gpt-5"),
+            finished(local(14), 2.0, "gpt-5-codex"),
+            finished("2025-05-30T10:00:00Z".to_string(), 9.0, "gpt-5"),
+        ];
+        let today_block = active_or_today(blocks.clone(), today, DayTimezone::Local).unwrap();
+        assert!(!today_block.is_active);
+        assert_eq!(today_block.cost_usd, Some(3.5));
+        assert_eq!(today_block.token_counts.input_tokens, 2000);
+        assert_eq!(today_block.models, vec!["gpt-5", "gpt-5-codex"]);
+
+        let cache = cache(FetchPhase::Completed, true, Some(today_block));
+        let entries = menu_entries(&cache, &Settings::default(), &[], menu_now());
+        assert!(entries.contains(&MenuEntry::disabled("session_title", "Today (no active session)")));
+        assert_eq!(active_or_today(blocks[2..].to_vec(), today, DayTimezone::Local).map(|b| b.id), None);
+
+        let mut with_active = blocks;
+        with_active[1].is_active = true;
+        assert_eq!(active_or_today(with_active, today, DayTimezone::Local).unwrap().cost_usd, Some(2.0));
+    }
+
+    #[test]
+    fn month_compares_projection_with_complete_months() {
+        // June 2025 has 30 days; $5.9 over 5 days projects to $35.40
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 6, 5).unwrap();
+        let mut history = vec![
+            entry("2025-04-20", 100.0),
+            entry("2025-05-01", 10.0),
+            entry("2025-05-31", 20.0),
+            entry("2025-06-01", 5.9),
+        ];
+        assert!((projected_month_cost(&history, today) - 35.4).abs() < 1e-9);
+        // April started mid-month, so only May's $30 is the baseline
+        assert_eq!(month_vs_average(&history, today).as_deref(), Some("This month vs avg: ▲ 18%"));
+
+        history.retain(|entry| entry.date.starts_with("2025-06"));
+        assert_eq!(month_vs_average(&history, today), None);
+    }
+
+    #[test]
+    fn model_families_sum_versions_together() {
+        let totals = vec![
+            ("claude-sonnet-4-20250514".to_string(), ModelTotals { cost_usd: Some(2.0), input_tokens: 100, output_tokens: 10 }),
+            ("claude-opus-4-20250514".to_string(), ModelTotals { cost_usd: Some(2.5), input_tokens: 50, output_tokens: 5 }),
+            ("claude-3-5-sonnet-20241022".to_string(), ModelTotals { cost_usd: Some(1.0), input_tokens: 200, output_tokens: 20 }),
+            ("o3".to_string(), ModelTotals { cost_usd: None, input_tokens: 7, output_tokens: 1 }),
+        ];
+        let grouped = group_model_families(totals);
+        let names: Vec<&str> = grouped.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["Sonnet (all)", "Opus (all)", "o3"]);
+        assert_eq!(grouped[0].1.cost_usd, Some(3.0));
+        assert_eq!(grouped[0].1.input_tokens, 300);
+        assert_eq!(grouped[2].1.cost_usd, None);
+    }
+
+    #[test]
+    fn status_report_reflects_the_cache() {
+        let report = status_report(&SessionData::EMPTY, None, Duration::from_secs(120));
+        assert!(report.stale);
+        assert!(!report.ccusage_available);
+        assert_eq!(report.schema_used, None);
+
+        let mut cache = cache(FetchPhase::Completed, true, None);
+        cache.last_updated = Some(Instant::now());
+        cache.last_fetch_latency = Some(Duration::from_millis(840));
+        cache.data_source = Some(DataSource { schema: Schema::Daily, command: "npx" });
+        let record = FetchRecord {
+            timestamp: chrono::Local::now(),
+            outcome: FetchOutcome::Success,
+            latency_ms: 840,
+        };
+        let report = status_report(&cache, Some(&record), Duration::from_secs(120));
+        assert!(!report.stale);
+        assert_eq!(report.active_source, Some("npx"));
+        assert_eq!(report.schema_used, Some(Schema::Daily));
+        assert_eq!(report.fetch_latency_ms, Some(840));
+        assert_eq!(report.last_fetch_ts, Some(record.timestamp));
+    }
+
+    #[test]
+    fn cost_thresholds_alert_once_each() {
+        let thresholds = [25.0, 5.0, 10.0];
+        assert!(newly_crossed_thresholds(&thresholds, 4.99, &[]).is_empty());
+        assert_eq!(newly_crossed_thresholds(&thresholds, 12.0, &[]), vec![5.0, 10.0]);
+        assert!(newly_crossed_thresholds(&thresholds, 12.0, &[5.0, 10.0]).is_empty());
+        assert_eq!(newly_crossed_thresholds(&thresholds, 30.0, &[5.0, 10.0]), vec![25.0]);
+    }
+
+    #[test]
+    fn only_session_log_writes_trigger_a_refresh() {
+        use notify::event::{CreateKind, DataChange, ModifyKind};
+        let write = |kind, path: &str| notify::Event::new(kind).add_path(std::path::PathBuf::from(path));
+        let modified = notify::EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        assert!(is_usage_write(&write(modified, "/logs/session.jsonl")));
+        assert!(is_usage_write(&write(notify::EventKind::Create(CreateKind::File), "/logs/new.jsonl")));
+        assert!(!is_usage_write(&write(modified, "/logs/notes.txt")));
+        assert!(!is_usage_write(&write(notify::EventKind::Remove(notify::event::RemoveKind::File), "/logs/a.jsonl")));
+    }
+
+    #[test]
+    fn block_countdown_reads_hours_and_minutes() {
+        let now = menu_now();
+        let ending = |minutes: i64| BlockData {
+            start_time: (now + chrono::Duration::minutes(minutes - 300)).to_rfc3339(),
+            end_time: (now + chrono::Duration::minutes(minutes)).to_rfc3339(),
+            ..block(1.25, &["gpt-5"])
+        };
+        assert_eq!(block_time_left(&ending(133), now).as_deref(), Some("2h 13m"));
+        assert_eq!(block_time_left(&ending(13), now).as_deref(), Some("13m"));
+        assert_eq!(block_time_left(&ending(-5), now).as_deref(), Some("0m"));
+        let finished = BlockData { is_active: false, ..ending(30) };
+        assert_eq!(block_time_left(&finished, now), None);
+
+        let settings = Settings {
+            show_active_block: true,
+            block_countdown_in_title: true,
+            ..Settings::default()
+        };
+        let mut cache = cache(FetchPhase::Completed, true, Some(block(3.5, &["gpt-5"])));
+        cache.cost_state = CostState::from_fetch(cache.active_block.as_ref(), true);
+        cache.block_view = Some(ending(133));
+        assert_eq!(tray_title_with_countdown(&cache, &settings, now).as_deref(), Some("$3.50 · 2h 13m"));
+        let entries = menu_entries(&cache, &settings, &[], now);
+        assert!(entries.contains(&MenuEntry::disabled("block_countdown", "▓▓▓▓▓▓░░░░ 2h 13m left")));
+    }
+
+    #[test]
+    fn block_progress_follows_the_window() {
+        let now = menu_now();
+        let window = |started_minutes_ago: i64| BlockData {
+            start_time: (now - chrono::Duration::minutes(started_minutes_ago)).to_rfc3339(),
+            end_time: (now + chrono::Duration::minutes(300 - started_minutes_ago)).to_rfc3339(),
+            ..block(1.25, &["gpt-5"])
+        };
+        assert_eq!(block_elapsed_fraction(&window(0), now), Some(0.0));
+        assert_eq!(block_elapsed_fraction(&window(150), now), Some(0.5));
+        assert_eq!(block_elapsed_fraction(&window(400), now), Some(1.0));
+        assert_eq!(block_elapsed_fraction(&block(1.25, &["gpt-5"]), now), None);
+        assert_eq!(progress_bar(0.0), "░░░░░░░░░░");
+        assert_eq!(progress_bar(0.56), "▓▓▓▓▓▓░░░░");
+        assert_eq!(progress_bar(1.0), "▓▓▓▓▓▓▓▓▓▓");
+    }
+
+    #[test]
+    fn title_modes_pick_cost_tokens_or_both() {
+        let mut block = block(4.21, &["gpt-5"]);
+        block.token_counts.input_tokens = 150_000;
+        block.token_counts.output_tokens = 32_000;
+        let title = |mode| {
+            let settings = Settings { title_mode: mode, ..Settings::default() };
+            format_title(Some(&block), CostState::Spent(4.21), &settings)
+        };
+        assert_eq!(title(TitleMode::Cost).as_deref(), Some("$4.21"));
+        assert_eq!(title(TitleMode::Tokens).as_deref(), Some("182K"));
+        assert_eq!(title(TitleMode::Both).as_deref(), Some("$4.21 · 182K"));
+        assert_eq!(title(TitleMode::IconOnly), None);
+
+        let tokens = Settings { title_mode: TitleMode::Tokens, ..Settings::default() };
+        assert_eq!(format_title(Some(&block), CostState::Unknown, &tokens), None);
+        assert_eq!(format_tokens_compact(1_500), "1.5K");
+        assert_eq!(format_tokens_compact(2_340_000), "2.3M");
+    }
+
+    #[test]
+    fn over_budget_marks_menu_and_title() {
+        let settings = Settings {
+            daily_budget_usd: Some(3.0),
+            ..Settings::default()
+        };
+        assert_eq!(format_title(None, CostState::Spent(3.5), &settings).as_deref(), Some("⚠️ $3.50"));
+        assert_eq!(format_title(None, CostState::Spent(2.5), &settings).as_deref(), Some("$2.50"));
+        let cache = cache(FetchPhase::Completed, true, Some(block(3.5, &["gpt-5"])));
+        let entries = menu_entries(&cache, &settings, &[], menu_now());
+        assert!(entries.contains(&MenuEntry::disabled("daily_budget", "⚠️ Budget: $3.50 / $3.00 (117%)")));
+    }
+
+    #[test]
+    fn both_sources_add_up_with_each_share_listed() {
+        let result = |cost: f64, model: &str, date: &str| FetchResult {
+            active_block: Some(block(cost, &[model])),
+            daily: vec![entry(date, cost)],
+            ccusage_available: true,
+            ..Default::default()
+        };
+        let combined = combine_results(
+            result(1.25, "gpt-5", "2025-06-02"),
+            result(2.0, "claude-sonnet-4-20250514", "Jun 02, 2025"),
+        );
+        let block = combined.active_block.clone().unwrap();
+        assert_eq!(block.cost_usd, Some(3.25));
+        assert_eq!(block.token_counts.input_tokens, 2000);
+        assert_eq!(block.models, vec!["claude-sonnet-4-20250514", "gpt-5"]);
+        assert_eq!(combined.daily.len(), 1);
+        assert_eq!(combined.daily[0].cost_usd, Some(3.25));
+        assert_eq!(combined.per_source.len(), 2);
+
+        let mut cache = cache(FetchPhase::Completed, true, combined.active_block);
+        cache.per_source = combined.per_source;
+        let entries = menu_entries(&cache, &Settings::default(), &[], menu_now());
+        assert!(entries.contains(&MenuEntry::disabled("source_total_codex", "Codex: $1.25 · In 1.0K / Out 0.5K")));
+        assert!(entries.contains(&MenuEntry::disabled(
+            "source_total_claude_code",
+            "Claude Code: $2.00 · In 1.0K / Out 0.5K"
+        )));
+
+        let codex_only = combine_results(result(1.25, "gpt-5", "2025-06-02"), FetchResult::default());
+        assert_eq!(codex_only.active_block.unwrap().cost_usd, Some(1.25));
+        assert_eq!(codex_only.per_source.len(), 1);
+    }
+
+    #[test]
+    fn bar_chart_scales_bars_to_the_busiest_day() {
+        let chart = bar_chart(&[0.0, 2.0, 4.0], 30, 10);
+        assert_eq!(chart.rgba.len(), 30 * 10 * 4);
+        let opaque = |x: u32, y: u32| chart.rgba[((y * 30 + x) * 4 + 3) as usize] == 255;
+        // Quiet day: baseline only
+        assert!(opaque(0, 9) && !opaque(0, 8));
+        // Half the peak: bottom five rows
+        assert!(opaque(10, 5) && !opaque(10, 4));
+        // Peak: full height, with a gap after the bar
+        assert!(opaque(20, 0) && opaque(26, 0) && !opaque(27, 0));
+    }
+
+    #[test]
+    fn projects_rank_todays_spend() {
+        let response: ProjectsResponse = serde_json::from_str(
+            r#"{"projects": {
+                "-Users-me-src-api": [{"date": "2025-06-01", "totalCost": 9.0}, {"date": "2025-06-02", "totalCost": 1.5}],
+                "-Users-me-src-web": [{"date": "2025-06-02", "totalCost": 4.0}],
+                "-Users-me-src-old": [{"date": "2025-06-01", "totalCost": 2.0}],
+                "/Users/me/src/cli": [{"date": "2025-06-02", "totalCost": 0.5}]
+            }, "totals": {"totalCost": 17.0}}"#,
+        )
+        .unwrap();
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+        let projects = top_projects(response, today, 2);
+        let costs: Vec<f64> = projects.iter().map(|(_, cost)| *cost).collect();
+        assert_eq!(costs, vec![4.0, 1.5]);
+        assert!(projects[0].0.ends_with("web"));
+        assert_eq!(project_label("/Users/me/src/cli"), "cli");
+
+        let mut cache = cache(FetchPhase::Completed, true, Some(block(1.0, &["gpt-5"])));
+        cache.projects = vec![("web".to_string(), 4.0)];
+        let entries = menu_entries(&cache, &Settings::default(), &[], menu_now());
+        let projects = entries
+            .iter()
+            .find_map(|entry| match entry {
+                MenuEntry::Submenu { id, entries, .. } if id == "projects" => Some(entries),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(projects, &vec![MenuEntry::disabled("project_0", "web: $4.00")]);
+    }
+
+    #[test]
+    fn model_rows_show_each_models_share() {
+        let claude_day: DailyEntry = serde_json::from_str(
+            r#"{"date": "2025-06-02", "inputTokens": 52000, "outputTokens": 14000, "costUSD": 3.6,
+                "modelsUsed": ["claude-opus-4-20250514", "claude-sonnet-4-20250514"],
+                "modelBreakdowns": [
+                    {"modelName": "claude-opus-4-20250514", "inputTokens": 40000, "outputTokens": 12000, "cost": 3.1},
+                    {"modelName": "claude-sonnet-4-20250514", "inputTokens": 12000, "outputTokens": 2000, "cost": 0.5}
+                ]}"#,
+        )
+        .unwrap();
+        let mut block = daily_to_block(&claude_day);
+        let today = menu_now().date_naive();
+        let entries = section_entries(Section::Models, &block, &SessionData::EMPTY, &Settings::default(), today);
+        let label = |id: &str| {
+            entries.iter().find_map(|entry| match entry {
+                MenuEntry::Item { id: item, label, .. } if item == id => Some(label.clone()),
+                _ => None,
+            })
+        };
+        assert_eq!(
+            label("model_claude-opus-4-20250514").unwrap(),
+            format!("{} — $3.10 / 40.0K in / 12.0K out", format_model_name("claude-opus-4-20250514"))
+        );
+
+        // Models without figures keep the bare name
+        block.model_stats.clear();
+        assert_eq!(model_row_label("GPT-5", block.model_stats.get("gpt-5")), "GPT-5");
+    }
+
+    #[test]
+    fn fetch_errors_read_as_menu_lines() {
+        let failed = FetchError::NonZeroExit {
+            stderr: stderr_summary(b"\nnpm ERR! code E404\nnpm ERR! 404 Not Found\n"),
+        };
+        assert_eq!(failed.message(), "ccusage failed: npm ERR! code E404");
+        assert_eq!(stderr_summary(&[b'x'; 100]).chars().count(), 81);
+        assert_eq!(
+            FetchError::ParseError { schema: Schema::Daily }.message(),
+            "Couldn't read ccusage daily output"
+        );
+        assert!(!failed.is_format_change());
+
+        let cache = SessionData {
+            last_error: Some(FetchError::CommandNotFound),
+            ..cache(FetchPhase::Completed, false, None)
+        };
+        let entries = menu_entries(&cache, &Settings::default(), &[], menu_now());
+        assert!(entries.contains(&MenuEntry::disabled("fetch_error", "ccusage not found")));
+        assert!(entries.iter().any(|entry| entry.id() == Some("install_msg")));
+    }
+
+    #[test]
+    fn refresh_speeds_up_while_active_and_backs_off_when_idle() {
+        let base = Duration::from_secs(120);
+        let minutes = |m: u64| Some(Duration::from_secs(m * 60));
+        assert_eq!(adaptive_refresh_interval(base, true, minutes(0)), Duration::from_secs(30));
+        assert_eq!(adaptive_refresh_interval(base, false, minutes(20)), base);
+        assert_eq!(adaptive_refresh_interval(base, false, minutes(61)), Duration::from_secs(600));
+        assert_eq!(adaptive_refresh_interval(base, false, None), Duration::from_secs(600));
+        // A slower configured interval is never sped up by idling
+        let slow = Duration::from_secs(900);
+        assert_eq!(adaptive_refresh_interval(slow, false, None), slow);
+    }
+
+    #[test]
+    fn menu_says_how_old_the_figures_are() {
+        assert_eq!(updated_ago(Duration::from_secs(40)), "Updated just now");
+        assert_eq!(updated_ago(Duration::from_secs(3 * 60 + 5)), "Updated 3m ago");
+        assert_eq!(updated_ago(Duration::from_secs(2 * 60 * 60 + 90)), "Updated 2h ago");
+
+        let mut cache = cache(FetchPhase::Completed, true, None);
+        cache.last_updated = Some(Instant::now());
+        let ids = menu_ids(&cache, &Settings::default());
+        assert_eq!(&ids[..3], &["ccusage_header", "last_updated", "-"]);
+    }
+
+    #[test]
+    fn old_figures_are_marked_stale() {
+        let mut cache = cache(FetchPhase::Completed, true, Some(block(3.5, &["gpt-5"])));
+        cache.cost_state = CostState::Spent(3.5);
+        let settings = Settings { stale_after_minutes: 15, ..Settings::default() };
+        let now = menu_now();
+
+        cache.last_success = Instant::now().checked_sub(Duration::from_secs(5 * 60));
+        assert_eq!(tray_title_with_countdown(&cache, &settings, now).as_deref(), Some("$3.50"));
+
+        cache.last_success = Instant::now().checked_sub(Duration::from_secs(20 * 60));
+        assert_eq!(tray_title_with_countdown(&cache, &settings, now).as_deref(), Some("$3.50 ⟳"));
+        assert!(menu_ids(&cache, &settings).iter().any(|id| id == "stale_warning"));
+
+        let never = Settings { stale_after_minutes: 0, ..Settings::default() };
+        assert_eq!(tray_title_with_countdown(&cache, &never, now).as_deref(), Some("$3.50"));
+    }
+
+    #[test]
+    fn refresh_over_earlier_figures_shows_progress() {
+        let mut cache = cache(
+            FetchPhase::InProgress { has_result: true },
+            true,
+            Some(block(3.5, &["gpt-5"])),
+        );
+        cache.cost_state = CostState::Spent(3.5);
+        let settings = Settings::default();
+        assert_eq!(tray_title_with_countdown(&cache, &settings, menu_now()).as_deref(), Some("$3.50 …"));
+        assert!(menu_ids(&cache, &settings).iter().any(|id| id == "refreshing"));
+
+        cache.fetch_phase = FetchPhase::Completed;
+        assert_eq!(tray_title_with_countdown(&cache, &settings, menu_now()).as_deref(), Some("$3.50"));
+        assert!(!menu_ids(&cache, &settings).iter().any(|id| id == "refreshing"));
+    }
+
+    #[test]
+    fn token_limit_warns_from_eighty_percent() {
+        let mut view = block(1.0, &["claude-opus-4"]);
+        view.token_counts.input_tokens = 4_000_000;
+        view.token_counts.output_tokens = 100_000;
+        let fixed = Some(settings::TokenLimit::Tokens(5_000_000));
+        assert_eq!(block_token_usage(&view, fixed), Some((4_100_000, 5_000_000)));
+        assert_eq!(token_limit_line(4_100_000, 5_000_000), "⚠ Block tokens: 4.1M / 5.0M (82%)");
+        assert_eq!(token_limit_line(1_500, 5_000_000), "Block tokens: 1.5K / 5.0M (0%)");
+
+        // "max" needs ccusage's figure; without it there's no line
+        let max = Some(settings::TokenLimit::Named(settings::NamedTokenLimit::Max));
+        assert_eq!(block_token_usage(&view, max), None);
+        view.token_limit_status = Some(TokenLimitStatus { limit: 8_200_000 });
+        assert_eq!(block_token_usage(&view, max), Some((4_100_000, 8_200_000)));
+        assert_eq!(block_token_usage(&view, None), None);
+    }
+
+    #[test]
+    fn block_changes_are_announced_after_the_first() {
+        use chrono::TimeZone;
+        let at = |h| chrono::Local.with_ymd_and_hms(2025, 6, 2, h, 0, 0).unwrap();
+        let window = |id: &str, end_hour| BlockData {
+            id: id.to_string(),
+            end_time: at(end_hour).to_rfc3339(),
+            ..block(1.0, &["gpt-5"])
+        };
+        let mut watched = None;
+
+        assert_eq!(block_events(&mut watched, Some(&window("a", 12)), at(9)), vec![]);
+        assert_eq!(block_events(&mut watched, Some(&window("a", 12)), at(11)), vec![]);
+        // The window runs out before ccusage reports anything new
+        assert_eq!(block_events(&mut watched, None, at(12)), vec![BlockEvent::Reset]);
+        assert_eq!(block_events(&mut watched, None, at(13)), vec![]);
+        assert_eq!(
+            block_events(&mut watched, Some(&window("b", 18)), at(13)),
+            vec![BlockEvent::Started { ends_at: Some("18:00".to_string()) }]
+        );
+    }
 }
@@ -0,0 +1,278 @@
+//! Daily usage read straight from the Claude Code and Codex session logs, so a
+//! refresh needs neither Node nor npx. The ccusage CLI stays the fallback
+//! whenever the logs can't give a complete answer.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde_json::Value;
+
+use crate::settings::{DayTimezone, ModelPrice};
+use crate::{model_setting, priced_cost, CliTool, DailyEntry, ModelStats};
+
+/// One model call's usage
+#[derive(Debug, Clone, PartialEq)]
+struct Usage {
+    date: chrono::NaiveDate,
+    model: String,
+    input_tokens: u64,
+    cached_input_tokens: u64,
+    output_tokens: u64,
+    cost_usd: Option<f64>,
+}
+
+// Claude Code has logged under both locations; Codex honours CODEX_HOME
+pub(crate) fn log_roots(tool: CliTool) -> Vec<PathBuf> {
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+    match tool {
+        CliTool::Claude => vec![
+            home.join(".claude").join("projects"),
+            home.join(".config").join("claude").join("projects"),
+        ],
+        CliTool::Codex => {
+            let codex_home = std::env::var_os("CODEX_HOME")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(".codex"));
+            vec![codex_home.join("sessions")]
+        }
+    }
+}
+
+// Every .jsonl under `dir` modified since `since`; older files can't hold
+// days the history keeps
+fn jsonl_files(dir: &Path, since: SystemTime, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            jsonl_files(&path, since, files);
+        } else if path.extension().is_some_and(|ext| ext == "jsonl")
+            && metadata.modified().map_or(true, |modified| modified >= since)
+        {
+            files.push(path);
+        }
+    }
+}
+
+fn timestamp_date(value: &Value, timezone: DayTimezone) -> Option<chrono::NaiveDate> {
+    let timestamp = chrono::DateTime::parse_from_rfc3339(value.get("timestamp")?.as_str()?).ok()?;
+    Some(timezone.date_of(&timestamp))
+}
+
+// An assistant message with usage, plus the key ccusage dedupes on: a resumed
+// session logs the same response again
+fn parse_claude_line(line: &str, timezone: DayTimezone) -> Option<(Option<String>, Usage)> {
+    let value: Value = serde_json::from_str(line).ok()?;
+    let message = value.get("message")?;
+    let usage = message.get("usage")?;
+    let model = message.get("model").and_then(Value::as_str).unwrap_or("unknown");
+    if model == "<synthetic>" {
+        return None;
+    }
+    let count = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+    let key = match (
+        message.get("id").and_then(Value::as_str),
+        value.get("requestId").and_then(Value::as_str),
+    ) {
+        (Some(id), Some(request)) => Some(format!("{}:{}", id, request)),
+        _ => None,
+    };
+    Some((
+        key,
+        Usage {
+            date: timestamp_date(&value, timezone)?,
+            model: model.to_string(),
+            // Cache writes bill as input
+            input_tokens: count("input_tokens") + count("cache_creation_input_tokens"),
+            cached_input_tokens: count("cache_read_input_tokens"),
+            output_tokens: count("output_tokens"),
+            cost_usd: value.get("costUSD").and_then(Value::as_f64),
+        },
+    ))
+}
+
+// Codex logs the model once per turn and per-call usage in token_count events
+fn parse_codex_log(contents: &str, timezone: DayTimezone) -> Vec<Usage> {
+    let mut model: Option<String> = None;
+    let mut usages = Vec::new();
+    for line in contents.lines() {
+        let Ok(value) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let Some(payload) = value.get("payload") else {
+            continue;
+        };
+        match value.get("type").and_then(Value::as_str) {
+            Some("turn_context") => {
+                if let Some(turn_model) = payload.get("model").and_then(Value::as_str) {
+                    model = Some(turn_model.to_string());
+                }
+            }
+            Some("event_msg") if payload.get("type").and_then(Value::as_str) == Some("token_count") => {
+                let Some(last) = payload.get("info").and_then(|info| info.get("last_token_usage")) else {
+                    continue;
+                };
+                let Some(date) = timestamp_date(&value, timezone) else {
+                    continue;
+                };
+                let count = |key: &str| last.get(key).and_then(Value::as_u64).unwrap_or(0);
+                usages.push(Usage {
+                    date,
+                    model: model.clone().unwrap_or_else(|| "unknown".to_string()),
+                    input_tokens: count("input_tokens"),
+                    cached_input_tokens: count("cached_input_tokens"),
+                    output_tokens: count("output_tokens"),
+                    cost_usd: None,
+                });
+            }
+            _ => {}
+        }
+    }
+    usages
+}
+
+fn read_usages(tool: CliTool, since: SystemTime, timezone: DayTimezone) -> Vec<Usage> {
+    let mut files = Vec::new();
+    for root in log_roots(tool) {
+        jsonl_files(&root, since, &mut files);
+    }
+    let mut seen = HashSet::new();
+    let mut usages = Vec::new();
+    for file in files {
+        let Ok(contents) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+        match tool {
+            CliTool::Claude => {
+                for (key, usage) in contents.lines().filter_map(|line| parse_claude_line(line, timezone)) {
+                    if key.map_or(true, |key| seen.insert(key)) {
+                        usages.push(usage);
+                    }
+                }
+            }
+            CliTool::Codex => usages.extend(parse_codex_log(&contents, timezone)),
+        }
+    }
+    usages
+}
+
+// Per-day sums, oldest first. None when any call has neither a logged cost nor
+// a configured price, so a partial figure never passes for the day's total.
+fn daily_entries(usages: Vec<Usage>, prices: &HashMap<String, ModelPrice>) -> Option<Vec<DailyEntry>> {
+    let mut days: BTreeMap<chrono::NaiveDate, DailyEntry> = BTreeMap::new();
+    for usage in usages {
+        let cost = match usage.cost_usd {
+            Some(cost) => cost,
+            None => {
+                let price = model_setting(prices, &usage.model)?;
+                priced_cost(price, usage.input_tokens, usage.cached_input_tokens, usage.output_tokens)
+            }
+        };
+        let entry = days.entry(usage.date).or_insert_with(|| DailyEntry {
+            date: usage.date.format("%Y-%m-%d").to_string(),
+            input_tokens: 0,
+            cached_input_tokens: 0,
+            output_tokens: 0,
+            total_tokens: 0,
+            cost_usd: Some(0.0),
+            models: HashMap::new(),
+        });
+        let total = usage.input_tokens + usage.cached_input_tokens + usage.output_tokens;
+        entry.input_tokens += usage.input_tokens;
+        entry.cached_input_tokens += usage.cached_input_tokens;
+        entry.output_tokens += usage.output_tokens;
+        entry.total_tokens += total;
+        *entry.cost_usd.get_or_insert(0.0) += cost;
+
+        let stats: &mut ModelStats = entry.models.entry(usage.model).or_default();
+        *stats.input_tokens.get_or_insert(0) += usage.input_tokens;
+        *stats.cached_input_tokens.get_or_insert(0) += usage.cached_input_tokens;
+        *stats.output_tokens.get_or_insert(0) += usage.output_tokens;
+        *stats.total_tokens.get_or_insert(0) += total;
+        *stats.cost_usd.get_or_insert(0.0) += cost;
+    }
+    Some(days.into_values().collect())
+}
+
+/// Daily entries for `tools` from their session logs, covering at most
+/// `max_days`. None when there are no logs or they can't all be priced.
+pub(crate) fn read_daily(
+    tools: &[CliTool],
+    timezone: DayTimezone,
+    prices: &HashMap<String, ModelPrice>,
+    max_days: usize,
+) -> Option<Vec<DailyEntry>> {
+    let since = SystemTime::now()
+        .checked_sub(Duration::from_secs(max_days as u64 * 24 * 60 * 60))
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let usages: Vec<Usage> = tools
+        .iter()
+        .flat_map(|tool| read_usages(*tool, since, timezone))
+        .collect();
+    if usages.is_empty() {
+        return None;
+    }
+    daily_entries(usages, prices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLAUDE_LINE: &str = r#"{"timestamp":"2025-06-02T10:00:00Z","requestId":"req_1","costUSD":0.25,"message":{"id":"msg_1","model":"claude-sonnet-4-20250514","usage":{"input_tokens":100,"cache_creation_input_tokens":20,"cache_read_input_tokens":300,"output_tokens":50}}}"#;
+
+    #[test]
+    fn claude_line_reads_usage_and_dedupe_key() {
+        let (key, usage) = parse_claude_line(CLAUDE_LINE, DayTimezone::Utc).unwrap();
+        assert_eq!(key.as_deref(), Some("msg_1:req_1"));
+        assert_eq!(usage.date, chrono::NaiveDate::from_ymd_opt(2025, 6, 2).unwrap());
+        assert_eq!(usage.input_tokens, 120);
+        assert_eq!(usage.cached_input_tokens, 300);
+        assert_eq!(usage.cost_usd, Some(0.25));
+        assert!(parse_claude_line(r#"{"type":"user","message":{"content":"hi"}}"#, DayTimezone::Utc).is_none());
+    }
+
+    #[test]
+    fn codex_log_attributes_usage_to_the_turn_model() {
+        let log = [
+            r#"{"timestamp":"2025-06-02T10:00:00Z","type":"turn_context","payload":{"model":"gpt-5-codex"}}"#,
+            r#"{"timestamp":"2025-06-02T10:00:05Z","type":"event_msg","payload":{"type":"token_count","info":null}}"#,
+            r#"{"timestamp":"2025-06-02T10:00:09Z","type":"event_msg","payload":{"type":"token_count","info":{"last_token_usage":{"input_tokens":1000,"cached_input_tokens":400,"output_tokens":200}}}}"#,
+        ]
+        .join("\n");
+        let usages = parse_codex_log(&log, DayTimezone::Utc);
+        assert_eq!(usages.len(), 1);
+        assert_eq!(usages[0].model, "gpt-5-codex");
+        assert_eq!(usages[0].output_tokens, 200);
+    }
+
+    #[test]
+    fn unpriced_usage_defers_to_ccusage() {
+        let (_, logged) = parse_claude_line(CLAUDE_LINE, DayTimezone::Utc).unwrap();
+        let unpriced = Usage {
+            model: "gpt-5".to_string(),
+            cost_usd: None,
+            ..logged.clone()
+        };
+        assert!(daily_entries(vec![logged.clone(), unpriced.clone()], &HashMap::new()).is_none());
+
+        let prices = HashMap::from([(
+            "gpt-5".to_string(),
+            ModelPrice { input: 1.0, output: 10.0, cached_input: Some(0.1) },
+        )]);
+        let daily = daily_entries(vec![logged, unpriced], &prices).unwrap();
+        assert_eq!(daily.len(), 1);
+        assert_eq!(daily[0].date, "2025-06-02");
+        // 0.25 logged + (120 * 1 + 300 * 0.1 + 50 * 10) / 1M
+        assert!((daily[0].cost_usd.unwrap() - 0.25065).abs() < 1e-9);
+        assert_eq!(daily[0].models.len(), 2);
+    }
+}
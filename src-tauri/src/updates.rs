@@ -0,0 +1,120 @@
+//! App updates through tauri-plugin-updater: "Check for Updates…" in the menu,
+//! plus an optional daily check in the background. Releases are signed; the
+//! key and the feed URL live under `plugins.updater` in tauri.conf.json. A
+//! build without the public key can't verify an update, so it leaves the
+//! plugin, the menu item and the background check out.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tauri::Manager;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::{settings, show_notification, SHUTTING_DOWN};
+
+// Background checks start a minute after launch, then run daily
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Set once the plugin is registered; the menu only offers checks after that
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// Whether the `plugins.updater` config carries a public key to verify with
+fn has_pubkey(updater: Option<&serde_json::Value>) -> bool {
+    updater
+        .and_then(|updater| updater.get("pubkey"))
+        .and_then(|pubkey| pubkey.as_str())
+        .is_some_and(|pubkey| !pubkey.trim().is_empty())
+}
+
+/// Register the updater and start the daily checks, if this build has a
+/// public key
+pub(crate) fn enable(app: &tauri::AppHandle) {
+    if !has_pubkey(app.config().plugins.0.get("updater")) {
+        eprintln!("No updater public key configured; updates are off");
+        return;
+    }
+    if let Err(e) = app.plugin(tauri_plugin_updater::Builder::new().build()) {
+        eprintln!("Failed to start the updater: {}", e);
+        return;
+    }
+    ENABLED.store(true, Ordering::Relaxed);
+    tauri::async_runtime::spawn(check_periodically(app.clone()));
+}
+
+async fn install(app: tauri::AppHandle, update: Update) {
+    match update.download_and_install(|_, _| {}, || {}).await {
+        Ok(()) => app.restart(),
+        Err(e) => show_notification("CCUsage", &format!("Update failed: {}", e)),
+    }
+}
+
+/// Look for a newer release and offer to install it. `interactive` checks come
+/// from the menu and also report "up to date" and failures.
+pub(crate) async fn check(app: tauri::AppHandle, interactive: bool) {
+    let found = match app.updater() {
+        Ok(updater) => updater.check().await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let update = match found {
+        Ok(Some(update)) => update,
+        Ok(None) => {
+            if interactive {
+                show_notification("CCUsage", "You're on the latest version");
+            }
+            return;
+        }
+        Err(e) => {
+            eprintln!("Update check failed: {}", e);
+            if interactive {
+                show_notification("CCUsage", "Couldn't check for updates");
+            }
+            return;
+        }
+    };
+    let app_handle = app.clone();
+    app.dialog()
+        .message(format!(
+            "CCUsage {} is available (you have {}). Install it and restart?",
+            update.version, update.current_version
+        ))
+        .title("CCUsage Update")
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Install and Restart".to_string(),
+            "Later".to_string(),
+        ))
+        .show(move |confirmed| {
+            if confirmed {
+                tauri::async_runtime::spawn(install(app_handle, update));
+            }
+        });
+}
+
+/// Daily background checks while `auto_check_updates` is on
+async fn check_periodically(app: tauri::AppHandle) {
+    tokio::time::sleep(FIRST_CHECK_DELAY).await;
+    while !SHUTTING_DOWN.load(Ordering::Relaxed) {
+        if settings::current().auto_check_updates {
+            check(app.clone(), false).await;
+        }
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_need_a_public_key() {
+        assert!(!has_pubkey(None));
+        assert!(!has_pubkey(Some(&serde_json::json!({ "endpoints": [], "pubkey": "" }))));
+        assert!(!has_pubkey(Some(&serde_json::json!({ "endpoints": [] }))));
+        assert!(has_pubkey(Some(&serde_json::json!({ "pubkey": "dW50cnVzdGVkIGNvbW1lbnQ6" }))));
+    }
+}
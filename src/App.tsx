@@ -1,39 +1,132 @@
-import { useState } from "react";
+import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import "./App.css";
 
+// The fields this window edits; everything else is passed back untouched
+type Settings = {
+  tool_preference: "codex" | "claude" | "auto" | "both";
+  title_mode: "cost" | "tokens" | "both" | "icon_only";
+  refresh_interval_secs: number;
+  daily_budget_usd: number | null;
+  include_cache_tokens: boolean;
+  show_dock_icon: boolean;
+  launch_at_login: boolean;
+  [key: string]: unknown;
+};
+
+type UsageSnapshot = {
+  title: string | null;
+  today: { cost_usd: number | null } | null;
+};
+
 function App() {
-  const [greetMsg, setGreetMsg] = useState("");
-  const [name, setName] = useState("");
+  const [settings, setSettings] = useState<Settings | null>(null);
+  const [snapshot, setSnapshot] = useState<UsageSnapshot | null>(null);
+  const [status, setStatus] = useState("");
+
+  useEffect(() => {
+    invoke<Settings>("get_settings").then(setSettings);
+    invoke<UsageSnapshot>("get_usage_snapshot").then(setSnapshot);
+  }, []);
 
-  async function greet() {
-    setGreetMsg(await invoke("greet", { name }));
+  if (!settings) {
+    return <main className="container">Loading…</main>;
+  }
+
+  const change = (patch: Partial<Settings>) => setSettings({ ...settings, ...patch });
+
+  async function save() {
+    try {
+      await invoke("set_settings", { settings });
+      setStatus("Saved");
+    } catch (e) {
+      setStatus(`Couldn't save: ${e}`);
+    }
   }
 
   return (
     <main className="container">
-      <h1>CC Usage Monitor</h1>
-      
-      <p>This is a minimal menubar app for macOS.</p>
-      
-      <form
-        className="row"
-        onSubmit={(e) => {
-          e.preventDefault();
-          greet();
-        }}
-      >
+      <h1>Preferences</h1>
+      {snapshot && <p>Menubar now shows: {snapshot.title ?? "(icon only)"}</p>}
+
+      <label>
+        Data source{" "}
+        <select
+          value={settings.tool_preference}
+          onChange={(e) => change({ tool_preference: e.currentTarget.value as Settings["tool_preference"] })}
+        >
+          <option value="codex">Codex (@ccusage/codex)</option>
+          <option value="claude">Claude Code (ccusage)</option>
+          <option value="auto">Auto (whichever has usage today)</option>
+          <option value="both">Both (combined)</option>
+        </select>
+      </label>
+
+      <label>
+        Menubar title{" "}
+        <select
+          value={settings.title_mode}
+          onChange={(e) => change({ title_mode: e.currentTarget.value as Settings["title_mode"] })}
+        >
+          <option value="cost">Cost</option>
+          <option value="tokens">Tokens</option>
+          <option value="both">Cost and tokens</option>
+          <option value="icon_only">Icon only</option>
+        </select>
+      </label>
+
+      <label>
+        Refresh every (seconds){" "}
         <input
-          id="greet-input"
-          onChange={(e) => setName(e.currentTarget.value)}
-          placeholder="Enter a name..."
+          type="number"
+          min={30}
+          value={settings.refresh_interval_secs}
+          onChange={(e) => change({ refresh_interval_secs: Number(e.currentTarget.value) })}
         />
-        <button type="submit">Greet</button>
-      </form>
-      
-      <p>{greetMsg}</p>
+      </label>
+
+      <label>
+        Daily budget (USD){" "}
+        <input
+          type="number"
+          min={0}
+          step="0.01"
+          value={settings.daily_budget_usd ?? ""}
+          onChange={(e) =>
+            change({ daily_budget_usd: e.currentTarget.value === "" ? null : Number(e.currentTarget.value) })
+          }
+        />
+      </label>
+
+      <label>
+        <input
+          type="checkbox"
+          checked={settings.include_cache_tokens}
+          onChange={(e) => change({ include_cache_tokens: e.currentTarget.checked })}
+        />{" "}
+        Include cache tokens
+      </label>
+      <label>
+        <input
+          type="checkbox"
+          checked={settings.show_dock_icon}
+          onChange={(e) => change({ show_dock_icon: e.currentTarget.checked })}
+        />{" "}
+        Show in Dock
+      </label>
+      <label>
+        <input
+          type="checkbox"
+          checked={settings.launch_at_login}
+          onChange={(e) => change({ launch_at_login: e.currentTarget.checked })}
+        />{" "}
+        Launch at Login
+      </label>
+
+      <button onClick={save}>Save</button>
+      <p>{status}</p>
     </main>
   );
 }
 
-export default App;
\ No newline at end of file
+export default App;
@@ -0,0 +1,213 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import "./App.css";
+
+// One day of stored history, as returned by `get_daily_history`
+type DayPoint = {
+  date: string; // YYYY-MM-DD
+  cost_usd: number;
+};
+
+// Chart data from `get_dashboard_data`, and from the "dashboard-updated" event
+// sent after every refresh
+type DashboardDay = {
+  date: string;
+  cost_usd: number;
+  cached_tokens: number;
+  uncached_tokens: number;
+};
+
+type DashboardModel = {
+  name: string;
+  input_tokens: number;
+  output_tokens: number;
+  cost_usd: number | null;
+};
+
+type DashboardData = {
+  days: DashboardDay[];
+  models: DashboardModel[];
+};
+
+// A year of columns, one per week; weeks start on Monday like the menu's
+const WEEKS = 53;
+const LEVELS = 4;
+
+function isoDate(date: Date): string {
+  const month = String(date.getMonth() + 1).padStart(2, "0");
+  const day = String(date.getDate()).padStart(2, "0");
+  return `${date.getFullYear()}-${month}-${day}`;
+}
+
+// Days from the Monday WEEKS-1 weeks back through today, column by column
+function heatmapDays(today: Date): Date[] {
+  const start = new Date(today.getFullYear(), today.getMonth(), today.getDate());
+  start.setDate(start.getDate() - ((start.getDay() + 6) % 7) - (WEEKS - 1) * 7);
+  const days: Date[] = [];
+  for (const day = new Date(start); day <= today; day.setDate(day.getDate() + 1)) {
+    days.push(new Date(day));
+  }
+  return days;
+}
+
+// Shade 0 for no spend, else 1..LEVELS by share of the costliest day
+function level(cost: number, max: number): number {
+  if (cost <= 0 || max <= 0) {
+    return 0;
+  }
+  return Math.max(1, Math.ceil((cost / max) * LEVELS));
+}
+
+function Heatmap({ history }: { history: DayPoint[] }) {
+  const costs = new Map(history.map((point) => [point.date, point.cost_usd]));
+  const days = heatmapDays(new Date());
+  const max = Math.max(0, ...days.map((day) => costs.get(isoDate(day)) ?? 0));
+
+  return (
+    <div className="heatmap">
+      {days.map((day) => {
+        const date = isoDate(day);
+        const cost = costs.get(date) ?? 0;
+        return (
+          <div
+            key={date}
+            className={`heatmap-day level-${level(cost, max)}`}
+            title={`${date}: $${cost.toFixed(2)}`}
+          />
+        );
+      })}
+    </div>
+  );
+}
+
+const CHART_WIDTH = 560;
+const CHART_HEIGHT = 140;
+
+function formatTokens(tokens: number): string {
+  if (tokens >= 1_000_000) {
+    return `${(tokens / 1_000_000).toFixed(1)}M`;
+  }
+  return `${(tokens / 1000).toFixed(0)}K`;
+}
+
+// Daily cost as a line, with the costliest day labelled on the axis
+function CostChart({ days }: { days: DashboardDay[] }) {
+  const max = Math.max(0, ...days.map((day) => day.cost_usd));
+  const step = CHART_WIDTH / Math.max(1, days.length - 1);
+  const y = (cost: number) => CHART_HEIGHT - (max > 0 ? (cost / max) * CHART_HEIGHT : 0);
+  const points = days.map((day, i) => `${i * step},${y(day.cost_usd)}`).join(" ");
+
+  return (
+    <svg className="chart" viewBox={`0 -4 ${CHART_WIDTH} ${CHART_HEIGHT + 8}`}>
+      <polyline className="chart-line" points={points} />
+      {days.map((day, i) => (
+        <circle key={day.date} className="chart-point" cx={i * step} cy={y(day.cost_usd)} r={2.5}>
+          <title>{`${day.date}: $${day.cost_usd.toFixed(2)}`}</title>
+        </circle>
+      ))}
+      <text className="chart-label" x={2} y={10}>
+        ${max.toFixed(2)}
+      </text>
+    </svg>
+  );
+}
+
+// Cache and non-cache tokens per day, stacked
+function CacheChart({ days }: { days: DashboardDay[] }) {
+  const max = Math.max(0, ...days.map((day) => day.cached_tokens + day.uncached_tokens));
+  const slot = CHART_WIDTH / Math.max(1, days.length);
+  const height = (tokens: number) => (max > 0 ? (tokens / max) * CHART_HEIGHT : 0);
+
+  return (
+    <svg className="chart" viewBox={`0 0 ${CHART_WIDTH} ${CHART_HEIGHT}`}>
+      {days.map((day, i) => {
+        const uncached = height(day.uncached_tokens);
+        const cached = height(day.cached_tokens);
+        return (
+          <g key={day.date}>
+            <title>
+              {`${day.date}: ${formatTokens(day.uncached_tokens)} non-cache, ${formatTokens(day.cached_tokens)} cache`}
+            </title>
+            <rect className="bar-uncached" x={i * slot + 1} y={CHART_HEIGHT - uncached} width={slot - 2} height={uncached} />
+            <rect
+              className="bar-cached"
+              x={i * slot + 1}
+              y={CHART_HEIGHT - uncached - cached}
+              width={slot - 2}
+              height={cached}
+            />
+          </g>
+        );
+      })}
+    </svg>
+  );
+}
+
+// Input and output tokens per model, one row each
+function ModelChart({ models }: { models: DashboardModel[] }) {
+  if (models.length === 0) {
+    return <p>No per-model figures in this data source</p>;
+  }
+  const max = Math.max(...models.map((model) => model.input_tokens + model.output_tokens));
+  const share = (tokens: number) => `${max > 0 ? (tokens / max) * 100 : 0}%`;
+
+  return (
+    <div className="model-chart">
+      {models.map((model) => (
+        <div key={model.name} className="model-row">
+          <span className="model-name">{model.name}</span>
+          <span className="model-bar">
+            <span className="bar-input" style={{ width: share(model.input_tokens) }} />
+            <span className="bar-output" style={{ width: share(model.output_tokens) }} />
+          </span>
+          <span className="model-figures">
+            {formatTokens(model.input_tokens)} in / {formatTokens(model.output_tokens)} out
+            {model.cost_usd !== null && ` · $${model.cost_usd.toFixed(2)}`}
+          </span>
+        </div>
+      ))}
+    </div>
+  );
+}
+
+function Dashboard() {
+  const [history, setHistory] = useState<DayPoint[] | null>(null);
+  const [data, setData] = useState<DashboardData | null>(null);
+
+  useEffect(() => {
+    invoke<DayPoint[]>("get_daily_history").then(setHistory);
+    invoke<DashboardData>("get_dashboard_data").then(setData);
+    // Follow the app's refreshes while the window is open
+    const unlisten = listen<DashboardData>("dashboard-updated", (event) => {
+      setData(event.payload);
+      invoke<DayPoint[]>("get_daily_history").then(setHistory);
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
+  if (!history || !data) {
+    return <main className="dashboard">Loading…</main>;
+  }
+
+  const total = history.reduce((sum, point) => sum + point.cost_usd, 0);
+  return (
+    <main className="dashboard">
+      <h2>Daily cost</h2>
+      <Heatmap history={history} />
+      <h2>Last {data.days.length} days</h2>
+      <CostChart days={data.days} />
+      <h2>Cache vs non-cache tokens</h2>
+      <CacheChart days={data.days} />
+      <h2>Tokens by model</h2>
+      <ModelChart models={data.models} />
+      <p>
+        {history.length} days stored, ${total.toFixed(2)} in total
+      </p>
+    </main>
+  );
+}
+
+export default Dashboard;
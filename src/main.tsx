@@ -1,9 +1,14 @@
 import React from "react";
 import ReactDOM from "react-dom/client";
+import { getCurrentWindow } from "@tauri-apps/api/window";
 import App from "./App";
+import Dashboard from "./Dashboard";
+
+// Preferences and the dashboard load the same page; the window label decides
+const View = getCurrentWindow().label === "dashboard" ? Dashboard : App;
 
 ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
   <React.StrictMode>
-    <App />
+    <View />
   </React.StrictMode>,
 );